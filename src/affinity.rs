@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+// Matches the nodes whose label `key` has one of the given values
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelSelector {
+    pub key: String,
+    pub values: Vec<String>,
+}
+
+impl LabelSelector {
+    pub fn new(key: &str, values: &[&str]) -> Self {
+        LabelSelector {
+            key: key.to_string(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        labels.get(&self.key).is_some_and(|value| self.values.contains(value))
+    }
+}
+
+// Where a process may or should be placed
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constraint {
+    // Only on nodes matching the selector
+    RequiredAffinity(LabelSelector),
+    // On nodes matching the selector when there is one with room
+    PreferredAffinity(LabelSelector),
+    // Never on nodes matching the selector
+    AntiAffinity(LabelSelector),
+    // Never on the same node as any of these processes (e.g. replicas of a service)
+    AvoidProcesses { process_ids: Vec<u32> },
+}
+
+// Whether a node with these labels, running these processes, is allowed by the constraints
+pub fn is_allowed(constraints: &[Constraint], labels: &HashMap<String, String>, process_ids: &[u32]) -> bool {
+    constraints.iter().all(|constraint| match constraint {
+        Constraint::RequiredAffinity(selector) => selector.matches(labels),
+        Constraint::AntiAffinity(selector) => !selector.matches(labels),
+        Constraint::AvoidProcesses { process_ids: avoided } => !avoided.iter().any(|id| process_ids.contains(id)),
+        Constraint::PreferredAffinity(_) => true,
+    })
+}
+
+// Number of preferred affinities a node with these labels satisfies
+pub fn preference_score(constraints: &[Constraint], labels: &HashMap<String, String>) -> usize {
+    constraints
+        .iter()
+        .filter(|constraint| matches!(constraint, Constraint::PreferredAffinity(selector) if selector.matches(labels)))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_selector() {
+        let selector = LabelSelector::new("zone", &["a", "b"]);
+
+        assert!(selector.matches(&labels(&[("zone", "b")])));
+        assert!(!selector.matches(&labels(&[("zone", "c")])));
+        assert!(!selector.matches(&labels(&[("disk", "ssd")])));
+    }
+
+    #[test]
+    fn test_required_and_anti_affinity() {
+        let constraints = vec![
+            Constraint::RequiredAffinity(LabelSelector::new("disk", &["ssd"])),
+            Constraint::AntiAffinity(LabelSelector::new("zone", &["a"])),
+        ];
+
+        assert!(is_allowed(&constraints, &labels(&[("disk", "ssd"), ("zone", "b")]), &[]));
+        assert!(!is_allowed(&constraints, &labels(&[("disk", "ssd"), ("zone", "a")]), &[]));
+        assert!(!is_allowed(&constraints, &labels(&[("disk", "hdd"), ("zone", "b")]), &[]));
+    }
+
+    #[test]
+    fn test_avoid_processes() {
+        let constraints = vec![Constraint::AvoidProcesses { process_ids: vec![1, 2] }];
+
+        assert!(is_allowed(&constraints, &HashMap::new(), &[3]));
+        assert!(!is_allowed(&constraints, &HashMap::new(), &[3, 2]));
+    }
+
+    #[test]
+    fn test_preference_score() {
+        let constraints = vec![
+            Constraint::PreferredAffinity(LabelSelector::new("zone", &["a"])),
+            Constraint::PreferredAffinity(LabelSelector::new("disk", &["ssd"])),
+        ];
+
+        assert_eq!(preference_score(&constraints, &labels(&[("zone", "a"), ("disk", "ssd")])), 2);
+        assert_eq!(preference_score(&constraints, &labels(&[("zone", "b")])), 0);
+        assert!(is_allowed(&constraints, &labels(&[("zone", "b")]), &[]));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::node::Node;
+use crate::resource::Resources;
+use std::collections::HashMap;
+
+// Where the autoscaler gets new machines from and gives them back to (a cloud API, a pool of spares...)
+pub trait NodeProvider: Send {
+    // A new node with the given id, None if no machine is available
+    fn provision(&mut self, node_id: u32) -> Option<Node>;
+    // The node was removed from the cluster, its machine can be given back
+    fn release(&mut self, node_id: u32);
+}
+
+// Identical nodes, optionally up to a number of machines at once
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaticProvider {
+    pub node_resources: Resources,
+    pub limit: Option<usize>,
+    // Nodes provisioned and not released yet
+    pub provisioned: Vec<u32>,
+}
+
+impl StaticProvider {
+    pub fn new(node_resources: Resources) -> Self {
+        StaticProvider { node_resources, limit: None, provisioned: Vec::new() }
+    }
+}
+
+impl NodeProvider for StaticProvider {
+    fn provision(&mut self, node_id: u32) -> Option<Node> {
+        if self.limit.is_some_and(|limit| self.provisioned.len() >= limit) {
+            return None;
+        }
+        self.provisioned.push(node_id);
+        Some(Node::with_resources(node_id, self.node_resources.clone()))
+    }
+
+    fn release(&mut self, node_id: u32) {
+        self.provisioned.retain(|&id| id != node_id);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoscalerConfig {
+    pub min_nodes: usize,
+    pub max_nodes: usize,
+    // Cluster utilization (share of its most used resource) over which demand exceeds capacity,
+    // pending processes count as demand too
+    pub scale_up_utilization: f64,
+    // Time demand must exceed capacity before a node is added
+    pub scale_up_after: u64,
+    // Cluster utilization under which idle nodes may be removed
+    pub scale_down_utilization: f64,
+    // Time a node must stay idle before it is drained
+    pub scale_down_after: u64,
+    // Time between two scaling decisions
+    pub cooldown: u64,
+    // Timeout of the drain of a removed node
+    pub drain_timeout: u64,
+}
+
+impl Default for AutoscalerConfig {
+    fn default() -> Self {
+        AutoscalerConfig {
+            min_nodes: 1,
+            max_nodes: 10,
+            scale_up_utilization: 0.8,
+            scale_up_after: 5_000,
+            scale_down_utilization: 0.3,
+            scale_down_after: 30_000,
+            cooldown: 10_000,
+            drain_timeout: 10_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScalingEvent {
+    ScaledUp { node_id: u32, at: u64 },
+    // The node is being drained, its machine is released once it leaves the cluster
+    ScalingDown { node_id: u32, at: u64 },
+    Released { node_id: u32, at: u64 },
+}
+
+// Adds nodes while demand exceeds capacity and removes idle ones when it drops, see `Session::autoscale`
+pub struct Autoscaler {
+    pub config: AutoscalerConfig,
+    pub provider: Box<dyn NodeProvider>,
+    // Since when demand exceeds capacity
+    pub pressure_since: Option<u64>,
+    // Since when each node has been idle
+    pub idle_since: HashMap<u32, u64>,
+    pub last_scaled: Option<u64>,
+    // Nodes added by the autoscaler and still in the cluster, the only ones it removes
+    pub provisioned: Vec<u32>,
+    // Nodes being drained by the autoscaler
+    pub scaling_down: Vec<u32>,
+    pub events: Vec<ScalingEvent>,
+}
+
+impl Autoscaler {
+    pub fn new(config: AutoscalerConfig, provider: Box<dyn NodeProvider>) -> Self {
+        Autoscaler {
+            config,
+            provider,
+            pressure_since: None,
+            idle_since: HashMap::new(),
+            last_scaled: None,
+            provisioned: Vec::new(),
+            scaling_down: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn in_cooldown(&self, now: u64) -> bool {
+        self.last_scaled.is_some_and(|last_scaled| now < last_scaled + self.config.cooldown)
+    }
+
+    // Record whether demand exceeds capacity, returns true once it has for `scale_up_after`
+    pub fn observe_pressure(&mut self, under_pressure: bool, now: u64) -> bool {
+        if !under_pressure {
+            self.pressure_since = None;
+            return false;
+        }
+        let since = *self.pressure_since.get_or_insert(now);
+        now >= since + self.config.scale_up_after
+    }
+
+    // Record the idle nodes, returns the one idle for the longest if it has been for `scale_down_after`
+    pub fn observe_idle(&mut self, idle_nodes: &[u32], now: u64) -> Option<u32> {
+        self.idle_since.retain(|node_id, _| idle_nodes.contains(node_id));
+        for &node_id in idle_nodes {
+            self.idle_since.entry(node_id).or_insert(now);
+        }
+        self.idle_since
+            .iter()
+            .filter(|(_, &since)| now >= since + self.config.scale_down_after)
+            .min_by_key(|(&node_id, &since)| (since, node_id))
+            .map(|(&node_id, _)| node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_provider_limit() {
+        let mut provider = StaticProvider::new(Resources::new(4_096, 10_000, 2));
+        provider.limit = Some(1);
+
+        assert_eq!(provider.provision(5).unwrap().total_resources, Resources::new(4_096, 10_000, 2));
+        assert!(provider.provision(6).is_none());
+        provider.release(5);
+        assert!(provider.provision(6).is_some());
+    }
+
+    #[test]
+    fn test_pressure_must_be_sustained() {
+        let mut autoscaler = Autoscaler::new(AutoscalerConfig::default(), Box::new(StaticProvider::new(Resources::new(1, 1, 1))));
+
+        assert!(!autoscaler.observe_pressure(true, 1_000));
+        assert!(!autoscaler.observe_pressure(true, 5_999));
+        assert!(autoscaler.observe_pressure(true, 6_000));
+        // A calm moment resets the period
+        assert!(!autoscaler.observe_pressure(false, 7_000));
+        assert!(!autoscaler.observe_pressure(true, 8_000));
+    }
+
+    #[test]
+    fn test_longest_idle_node() {
+        let mut autoscaler = Autoscaler::new(AutoscalerConfig::default(), Box::new(StaticProvider::new(Resources::new(1, 1, 1))));
+
+        assert_eq!(autoscaler.observe_idle(&[2], 0), None);
+        assert_eq!(autoscaler.observe_idle(&[2, 3], 10_000), None);
+        assert_eq!(autoscaler.observe_idle(&[2, 3], 40_000), Some(2));
+        // Node 2 got work, node 3 hasn't been idle long enough
+        assert_eq!(autoscaler.observe_idle(&[3], 39_999), None);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::affinity::is_allowed;
+use crate::deadline::remaining_time;
+use crate::node::Node;
+use crate::process::Process;
+use crate::resource::Resources;
+
+// Capacity held for the blocked process at the head of the queue (EASY backfilling)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reservation {
+    pub process_id: u32,
+    pub node_id: u32,
+    // Projected time enough running processes have ended on the node for the process to fit
+    pub start_time: u64,
+    // Resources of the node still free at `start_time` once the process is started,
+    // processes running past `start_time` may use them without delaying it
+    pub extra: Resources,
+}
+
+impl Reservation {
+    // Whether the process ends before the reserved start time, processes without estimate never do
+    pub fn finishes_before(&self, process: &Process, now: u64) -> bool {
+        process.estimated_duration.is_some_and(|duration| now + duration <= self.start_time)
+    }
+}
+
+// Earliest node and time the process could start once the running processes end.
+// Only processes with an estimated duration are expected to end.
+pub fn reservation_for(process: &Process, nodes: &[&Node], now: u64) -> Option<Reservation> {
+    nodes
+        .iter()
+        .filter(|node| node.total_resources.can_allocate(&process.needed_resources))
+        .filter(|node| {
+            let process_ids: Vec<u32> = node.active_processes.iter().map(|p| p.id).collect();
+            is_allowed(&process.constraints, &node.labels, &process_ids)
+        })
+        .filter_map(|node| {
+            let mut ending: Vec<(u64, &Process)> = node
+                .active_processes
+                .iter()
+                .filter(|p| p.estimated_duration.is_some())
+                .map(|p| (now + remaining_time(p, now), p))
+                .collect();
+            ending.sort_by_key(|(end, _)| *end);
+
+            let mut free = node.available_resources.clone();
+            let mut start_time = now;
+            for (end, running) in ending {
+                if free.can_allocate(&process.needed_resources) {
+                    break;
+                }
+                free.deallocate(&running.needed_resources);
+                start_time = end;
+            }
+            if !free.allocate(&process.needed_resources) {
+                return None;
+            }
+            Some(Reservation { process_id: process.id, node_id: node.id, start_time, extra: free })
+        })
+        .min_by_key(|reservation| (reservation.start_time, reservation.node_id))
+}
+
+// Smallest amount of every resource
+pub fn min_resources(a: &Resources, b: &Resources) -> Resources {
+    Resources::new(a.ram.min(b.ram), a.disk_space.min(b.disk_space), a.threads.min(b.threads))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn running(id: u32, ram: u64, started_at: u64, duration: Option<u64>) -> Process {
+        let mut process = Process::new(id, format!("Process {}", id), Resources::new(ram, 1_000, 1));
+        process.started_at = Some(started_at);
+        process.estimated_duration = duration;
+        process
+    }
+
+    fn node(id: u32, processes: Vec<Process>) -> Node {
+        let mut node = Node::with_resources(id, Resources::new(8_192, 100_000, 8));
+        for process in processes {
+            node.allocate(&process.needed_resources);
+            node.active_processes.push(process);
+        }
+        node
+    }
+
+    #[test]
+    fn test_reservation_at_earliest_start() {
+        let a = node(1, vec![running(1, 4_096, 0, Some(1_000)), running(2, 4_096, 0, Some(3_000))]);
+        let b = node(2, vec![running(3, 6_144, 0, Some(2_000))]);
+        let big = Process::new(9, "Big".to_string(), Resources::new(6_144, 1_000, 1));
+
+        let reservation = reservation_for(&big, &[&a, &b], 500).unwrap();
+        // Node 1 only has room once both processes end, node 2 once its single process ends
+        assert_eq!(reservation.node_id, 2);
+        assert_eq!(reservation.start_time, 2_000);
+        assert_eq!(reservation.extra, Resources::new(2_048, 99_000, 7));
+    }
+
+    #[test]
+    fn test_no_reservation_without_estimates() {
+        let a = node(1, vec![running(1, 8_192, 0, None)]);
+        let big = Process::new(9, "Big".to_string(), Resources::new(4_096, 1_000, 1));
+
+        assert_eq!(reservation_for(&big, &[&a], 0), None);
+    }
+
+    #[test]
+    fn test_finishes_before() {
+        let reservation = Reservation {
+            process_id: 9,
+            node_id: 1,
+            start_time: 1_000,
+            extra: Resources::new(0, 0, 0),
+        };
+        let mut short = Process::new(1, "Short".to_string(), Resources::new(1, 1, 1));
+        short.estimated_duration = Some(400);
+
+        assert!(reservation.finishes_before(&short, 600));
+        assert!(!reservation.finishes_before(&short, 700));
+        short.estimated_duration = None;
+        assert!(!reservation.finishes_before(&short, 0));
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Time source used by the session (milliseconds)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Clock {
+    // Wall clock time since the UNIX epoch
+    System,
+    // Manually driven clock, used by tests and simulations
+    Simulated { now: u64 },
+}
+
+impl Clock {
+    pub fn simulated(start: u64) -> Self {
+        Clock::Simulated { now: start }
+    }
+
+    // Current time in milliseconds
+    pub fn now(&self) -> u64 {
+        match self {
+            Clock::System => {
+                let duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                duration.as_millis() as u64
+            }
+            Clock::Simulated { now } => *now,
+        }
+    }
+
+    // Move a simulated clock forward (no effect on the system clock)
+    pub fn advance(&mut self, millis: u64) {
+        if let Clock::Simulated { now } = self {
+            *now += millis;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulated_clock_advance() {
+        let mut clock = Clock::simulated(1_000);
+        assert_eq!(clock.now(), 1_000);
+
+        clock.advance(250);
+        assert_eq!(clock.now(), 1_250);
+    }
+
+    #[test]
+    fn test_system_clock_ignores_advance() {
+        let mut clock = Clock::System;
+        let before = clock.now();
+        clock.advance(1_000_000);
+
+        assert!(clock.now() < before + 1_000_000);
+    }
+}
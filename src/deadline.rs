@@ -0,0 +1,90 @@
+use crate::process::Process;
+use std::cmp::Ordering;
+use std::fmt;
+
+// Why a process was not admitted
+#[derive(Clone, Debug, PartialEq)]
+pub enum AdmissionError {
+    // With the current load the process would finish after its deadline
+    DeadlineUnreachable { process_id: u32, deadline: u64, estimated_completion: u64 },
+}
+
+impl fmt::Display for AdmissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdmissionError::DeadlineUnreachable { process_id, deadline, estimated_completion } => write!(
+                f,
+                "Process {} would finish at {}, after its deadline {}",
+                process_id, estimated_completion, deadline
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AdmissionError {}
+
+// A process that was still pending or running when its deadline passed, or finished after it
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeadlineMiss {
+    pub process_id: u32,
+    pub deadline: u64,
+    pub detected_at: u64,
+}
+
+// Earliest deadline first, processes without deadline last, then by priority
+pub fn edf_order(a: &Process, b: &Process) -> Ordering {
+    match (a.deadline, b.deadline) {
+        (Some(a_deadline), Some(b_deadline)) => a_deadline.cmp(&b_deadline),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+    .then(b.priority.cmp(&a.priority))
+}
+
+// Time the process still needs to run, 0 when unknown or already overdue
+pub fn remaining_time(process: &Process, now: u64) -> u64 {
+    match (process.started_at, process.estimated_duration) {
+        (Some(started_at), Some(duration)) => (started_at + duration).saturating_sub(now),
+        (None, Some(duration)) => duration,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::Priority;
+    use crate::resource::Resources;
+
+    fn process(id: u32, deadline: Option<u64>, priority: Priority) -> Process {
+        let mut process = Process::with_priority(id, format!("Process {}", id), Resources::new(1, 1, 1), priority);
+        process.deadline = deadline;
+        process
+    }
+
+    #[test]
+    fn test_edf_order() {
+        let mut processes = [
+            process(1, None, Priority::Critical),
+            process(2, Some(500), Priority::Normal),
+            process(3, Some(100), Priority::BestEffort),
+            process(4, Some(500), Priority::High),
+        ];
+        processes.sort_by(edf_order);
+
+        let ids: Vec<u32> = processes.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![3, 4, 2, 1]);
+    }
+
+    #[test]
+    fn test_remaining_time() {
+        let mut running = process(1, None, Priority::Normal);
+        running.estimated_duration = Some(1_000);
+        assert_eq!(remaining_time(&running, 0), 1_000);
+
+        running.started_at = Some(200);
+        assert_eq!(remaining_time(&running, 700), 500);
+        assert_eq!(remaining_time(&running, 5_000), 0);
+    }
+}
@@ -0,0 +1,42 @@
+// Progress of a node being drained before its removal
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrainState {
+    pub node_id: u32,
+    // Node that proposed the drain, receives the outcome
+    pub proposer_id: u32,
+    // Time allowed for the drain and time after which the remaining processes are requeued
+    pub timeout: u64,
+    pub deadline: u64,
+    // Processes being moved to other nodes through live migration
+    pub migrating: Vec<u32>,
+}
+
+impl DrainState {
+    pub fn new(node_id: u32, proposer_id: u32, started_at: u64, timeout: u64) -> Self {
+        DrainState {
+            node_id,
+            proposer_id,
+            timeout,
+            deadline: started_at + timeout,
+            migrating: Vec::new(),
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.deadline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_deadline() {
+        let drain = DrainState::new(1, 2, 1_000, 4_000);
+
+        assert!(!drain.is_expired(4_999));
+        assert!(drain.is_expired(5_000));
+        assert!(drain.migrating.is_empty());
+    }
+}
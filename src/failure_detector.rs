@@ -0,0 +1,229 @@
+use std::collections::{HashMap, VecDeque};
+
+pub const DEFAULT_PHI_THRESHOLD: f64 = 8.0;
+
+// Inter-arrival history of the heartbeats received from one node
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeartbeatHistory {
+    pub last_arrival: u64,
+    pub intervals: VecDeque<u64>,
+}
+
+impl HeartbeatHistory {
+    fn new(arrival: u64) -> Self {
+        HeartbeatHistory {
+            last_arrival: arrival,
+            intervals: VecDeque::new(),
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        self.intervals.iter().sum::<u64>() as f64 / self.intervals.len() as f64
+    }
+
+    fn std_deviation(&self) -> f64 {
+        let mean = self.mean();
+        let variance = self
+            .intervals
+            .iter()
+            .map(|&i| {
+                let diff = i as f64 - mean;
+                diff * diff
+            })
+            .sum::<f64>()
+            / self.intervals.len() as f64;
+        variance.sqrt()
+    }
+}
+
+// Phi-accrual failure detector (Hayashibara et al.)
+// Instead of a fixed timeout, it outputs a suspicion level (phi) that grows
+// the longer a node stays silent compared to its usual heartbeat rate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhiAccrualDetector {
+    pub threshold: f64,
+    // Maximum number of intervals kept per node
+    pub window_size: usize,
+    // Lower bound for the standard deviation, avoids over-sensitivity when heartbeats are regular
+    pub min_std_deviation: f64,
+    // Extra margin (ms) tolerated on top of the mean interval
+    pub acceptable_pause: u64,
+    // Latest time observed by this detector
+    pub now: u64,
+    pub histories: HashMap<u32, HeartbeatHistory>,
+}
+
+impl Default for PhiAccrualDetector {
+    fn default() -> Self {
+        PhiAccrualDetector::new(DEFAULT_PHI_THRESHOLD, 100, 100.0, 0)
+    }
+}
+
+impl PhiAccrualDetector {
+    pub fn new(threshold: f64, window_size: usize, min_std_deviation: f64, acceptable_pause: u64) -> Self {
+        PhiAccrualDetector {
+            threshold,
+            window_size,
+            min_std_deviation,
+            acceptable_pause,
+            now: 0,
+            histories: HashMap::new(),
+        }
+    }
+
+    // Record a heartbeat received from a node at the given time
+    pub fn heartbeat(&mut self, node_id: u32, arrival: u64) {
+        self.tick(arrival);
+
+        match self.histories.get_mut(&node_id) {
+            Some(history) => {
+                if arrival <= history.last_arrival {
+                    return; // Out of order or duplicated heartbeat
+                }
+                history.intervals.push_back(arrival - history.last_arrival);
+                if history.intervals.len() > self.window_size {
+                    history.intervals.pop_front();
+                }
+                history.last_arrival = arrival;
+            }
+            None => {
+                self.histories.insert(node_id, HeartbeatHistory::new(arrival));
+            }
+        }
+    }
+
+    // Advance the detector's notion of the current time
+    pub fn tick(&mut self, now: u64) {
+        if now > self.now {
+            self.now = now;
+        }
+    }
+
+    // Forget a node (e.g. after it has been removed from the cluster)
+    pub fn remove(&mut self, node_id: u32) {
+        self.histories.remove(&node_id);
+    }
+
+    // Suspicion level at the given time, None until at least one interval is known
+    pub fn phi_at(&self, node_id: u32, now: u64) -> Option<f64> {
+        let history = self.histories.get(&node_id)?;
+        if history.intervals.is_empty() {
+            return None;
+        }
+
+        let elapsed = now.saturating_sub(history.last_arrival) as f64;
+        let mean = history.mean() + self.acceptable_pause as f64;
+        let std_deviation = history.std_deviation().max(self.min_std_deviation);
+
+        // Logistic approximation of the normal cumulative distribution
+        let y = (elapsed - mean) / std_deviation;
+        let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+        let phi = if elapsed > mean {
+            -(e / (1.0 + e)).log10()
+        } else {
+            -(1.0 - 1.0 / (1.0 + e)).log10()
+        };
+        Some(phi)
+    }
+
+    // Suspicion level at the latest observed time
+    pub fn phi(&self, node_id: u32) -> Option<f64> {
+        self.phi_at(node_id, self.now)
+    }
+
+    // Some(true) if the node is suspected, None when there is not enough data
+    pub fn is_suspected(&self, node_id: u32) -> Option<bool> {
+        self.phi(node_id).map(|phi| phi >= self.threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+
+    fn regular_heartbeats(detector: &mut PhiAccrualDetector, clock: &mut Clock, node_id: u32, count: usize) {
+        for _ in 0..count {
+            clock.advance(1_000);
+            detector.heartbeat(node_id, clock.now());
+        }
+    }
+
+    #[test]
+    fn test_phi_unknown_without_intervals() {
+        let mut detector = PhiAccrualDetector::default();
+        assert_eq!(detector.phi(1), None);
+
+        detector.heartbeat(1, 1_000);
+        assert_eq!(detector.phi(1), None);
+        assert_eq!(detector.is_suspected(1), None);
+    }
+
+    #[test]
+    fn test_phi_low_for_regular_heartbeats() {
+        let mut clock = Clock::simulated(0);
+        let mut detector = PhiAccrualDetector::default();
+        regular_heartbeats(&mut detector, &mut clock, 1, 10);
+
+        clock.advance(1_000);
+        detector.tick(clock.now());
+
+        assert!(detector.phi(1).unwrap() < 1.0);
+        assert_eq!(detector.is_suspected(1), Some(false));
+    }
+
+    #[test]
+    fn test_phi_grows_with_silence() {
+        let mut clock = Clock::simulated(0);
+        let mut detector = PhiAccrualDetector::default();
+        regular_heartbeats(&mut detector, &mut clock, 1, 10);
+
+        let mut previous = detector.phi(1).unwrap();
+        for _ in 0..4 {
+            clock.advance(500);
+            let phi = detector.phi_at(1, clock.now()).unwrap();
+            assert!(phi >= previous);
+            previous = phi;
+        }
+
+        clock.advance(5_000);
+        detector.tick(clock.now());
+        assert_eq!(detector.is_suspected(1), Some(true));
+    }
+
+    #[test]
+    fn test_jitter_tolerated() {
+        let mut clock = Clock::simulated(0);
+        let mut detector = PhiAccrualDetector::default();
+
+        // Heartbeats alternate between 600ms and 1400ms
+        for i in 0..20 {
+            clock.advance(if i % 2 == 0 { 600 } else { 1_400 });
+            detector.heartbeat(1, clock.now());
+        }
+
+        // A late heartbeat within the usual jitter is not a failure
+        clock.advance(1_500);
+        detector.tick(clock.now());
+        assert_eq!(detector.is_suspected(1), Some(false));
+    }
+
+    #[test]
+    fn test_window_size_limits_history() {
+        let mut clock = Clock::simulated(0);
+        let mut detector = PhiAccrualDetector::new(DEFAULT_PHI_THRESHOLD, 5, 100.0, 0);
+        regular_heartbeats(&mut detector, &mut clock, 1, 20);
+
+        assert_eq!(detector.histories[&1].intervals.len(), 5);
+    }
+
+    #[test]
+    fn test_remove_node() {
+        let mut detector = PhiAccrualDetector::default();
+        detector.heartbeat(1, 1_000);
+        detector.heartbeat(1, 2_000);
+
+        detector.remove(1);
+        assert_eq!(detector.phi(1), None);
+    }
+}
@@ -0,0 +1,247 @@
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+// Faults affecting nodes and processes
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+    // The node stops until it is recovered
+    CrashNode { node_id: u32 },
+    // The node stops answering for a while, then resumes
+    PauseNode { node_id: u32, duration: u64 },
+    FailProcess { process_id: u32, reason: String },
+}
+
+// What happens to a single message in transit
+#[derive(Clone, Debug, PartialEq)]
+pub enum MessageFault {
+    Deliver,
+    Drop,
+    Delay(u64),
+    Duplicate,
+    // Held back so that messages sent after it arrive first
+    Reorder,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledFault {
+    pub at: u64,
+    pub fault: Fault,
+}
+
+// Injects faults from a schedule or at random.
+// All random decisions come from a seeded generator so chaos runs are reproducible.
+#[derive(Clone, Debug)]
+pub struct FaultInjector {
+    pub seed: u64,
+    rng: SmallRng,
+    pub schedule: Vec<ScheduledFault>,
+    // Message faults, probability per message
+    pub drop_probability: f64,
+    pub delay_probability: f64,
+    pub max_delay: u64,
+    pub duplicate_probability: f64,
+    pub reorder_probability: f64,
+    // Node and process faults, probability per call to `due_faults`
+    pub crash_probability: f64,
+    pub pause_probability: f64,
+    pub pause_duration: u64,
+    pub process_failure_probability: f64,
+    // Paused nodes and the time they resume
+    pub paused: HashMap<u32, u64>,
+    // Every fault injected so far, with its time
+    pub injected: Vec<(u64, Fault)>,
+}
+
+impl FaultInjector {
+    pub fn new(seed: u64) -> Self {
+        FaultInjector {
+            seed,
+            rng: SmallRng::seed_from_u64(seed),
+            schedule: Vec::new(),
+            drop_probability: 0.0,
+            delay_probability: 0.0,
+            max_delay: 1_000,
+            duplicate_probability: 0.0,
+            reorder_probability: 0.0,
+            crash_probability: 0.0,
+            pause_probability: 0.0,
+            pause_duration: 1_000,
+            process_failure_probability: 0.0,
+            paused: HashMap::new(),
+            injected: Vec::new(),
+        }
+    }
+
+    // Schedule a fault at a given time
+    pub fn schedule(&mut self, at: u64, fault: Fault) {
+        self.schedule.push(ScheduledFault { at, fault });
+        self.schedule.sort_by_key(|f| f.at);
+    }
+
+    // Decide the fate of a message being sent
+    pub fn message_fault(&mut self) -> MessageFault {
+        if self.chance(self.drop_probability) {
+            MessageFault::Drop
+        } else if self.chance(self.delay_probability) {
+            let delay = self.rng.gen_range(1..=self.max_delay.max(1));
+            MessageFault::Delay(delay)
+        } else if self.chance(self.duplicate_probability) {
+            MessageFault::Duplicate
+        } else if self.chance(self.reorder_probability) {
+            MessageFault::Reorder
+        } else {
+            MessageFault::Deliver
+        }
+    }
+
+    // Scheduled faults that are due plus random node and process faults.
+    // Random faults hit the nodes that are up, a scheduled pause of a halted node is skipped:
+    // it would bring a crashed node back up once over.
+    pub fn due_faults(&mut self, now: u64, node_ids: &[u32], halted_ids: &[u32], process_ids: &[u32]) -> Vec<Fault> {
+        let mut faults: Vec<Fault> = Vec::new();
+        while !self.schedule.is_empty() && self.schedule[0].at <= now {
+            match self.schedule.remove(0).fault {
+                Fault::PauseNode { node_id, .. } if halted_ids.contains(&node_id) => {
+                    println!("Node {} is already down, its pause is skipped", node_id);
+                }
+                fault => faults.push(fault),
+            }
+        }
+
+        if !node_ids.is_empty() {
+            if self.chance(self.crash_probability) {
+                let node_id = node_ids[self.rng.gen_range(0..node_ids.len())];
+                faults.push(Fault::CrashNode { node_id });
+            }
+            if self.chance(self.pause_probability) {
+                let node_id = node_ids[self.rng.gen_range(0..node_ids.len())];
+                faults.push(Fault::PauseNode { node_id, duration: self.pause_duration });
+            }
+        }
+        if !process_ids.is_empty() && self.chance(self.process_failure_probability) {
+            let process_id = process_ids[self.rng.gen_range(0..process_ids.len())];
+            faults.push(Fault::FailProcess { process_id, reason: "Injected fault".to_string() });
+        }
+
+        for fault in faults.iter() {
+            if let Fault::PauseNode { node_id, duration } = fault {
+                self.paused.insert(*node_id, now + duration);
+            }
+            self.injected.push((now, fault.clone()));
+        }
+        // A crashed node stays down until it is recovered, even if it was paused
+        for fault in faults.iter() {
+            if let Fault::CrashNode { node_id } = fault {
+                self.paused.remove(node_id);
+            }
+        }
+        faults
+    }
+
+    // Paused nodes whose pause is over
+    pub fn resume_due(&mut self, now: u64) -> Vec<u32> {
+        let mut resumed: Vec<u32> = self
+            .paused
+            .iter()
+            .filter(|(_, &until)| until <= now)
+            .map(|(&id, _)| id)
+            .collect();
+        resumed.sort();
+        for node_id in resumed.iter() {
+            self.paused.remove(node_id);
+        }
+        resumed
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.gen_bool(probability.min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_faults_by_default() {
+        let mut injector = FaultInjector::new(1);
+
+        for _ in 0..100 {
+            assert_eq!(injector.message_fault(), MessageFault::Deliver);
+        }
+        assert!(injector.due_faults(1_000, &[1, 2], &[], &[1]).is_empty());
+    }
+
+    #[test]
+    fn test_scheduled_faults() {
+        let mut injector = FaultInjector::new(1);
+        injector.schedule(2_000, Fault::CrashNode { node_id: 2 });
+        injector.schedule(1_000, Fault::PauseNode { node_id: 1, duration: 500 });
+
+        assert!(injector.due_faults(500, &[], &[], &[]).is_empty());
+        assert_eq!(
+            injector.due_faults(1_000, &[], &[], &[]),
+            vec![Fault::PauseNode { node_id: 1, duration: 500 }]
+        );
+        assert_eq!(injector.due_faults(5_000, &[], &[], &[]), vec![Fault::CrashNode { node_id: 2 }]);
+        assert_eq!(injector.injected.len(), 2);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let mut injector = FaultInjector::new(1);
+        injector.schedule(0, Fault::PauseNode { node_id: 3, duration: 1_000 });
+        injector.due_faults(0, &[], &[], &[]);
+
+        assert!(injector.resume_due(999).is_empty());
+        assert_eq!(injector.resume_due(1_000), vec![3]);
+        assert!(injector.paused.is_empty());
+    }
+
+    #[test]
+    fn test_crash_cancels_pause() {
+        let mut injector = FaultInjector::new(1);
+        injector.schedule(0, Fault::PauseNode { node_id: 3, duration: 1_000 });
+        injector.schedule(500, Fault::CrashNode { node_id: 3 });
+        injector.due_faults(0, &[], &[], &[]);
+        injector.due_faults(500, &[], &[], &[]);
+
+        assert!(injector.paused.is_empty());
+        assert!(injector.resume_due(1_000).is_empty());
+    }
+
+    #[test]
+    fn test_no_pause_of_halted_node() {
+        let mut injector = FaultInjector::new(1);
+        injector.schedule(2, Fault::PauseNode { node_id: 3, duration: 1_000 });
+
+        assert!(injector.due_faults(2, &[1, 2], &[3], &[]).is_empty());
+        assert!(injector.paused.is_empty());
+    }
+
+    #[test]
+    fn test_same_seed_same_decisions() {
+        let run = |seed: u64| {
+            let mut injector = FaultInjector::new(seed);
+            injector.drop_probability = 0.2;
+            injector.delay_probability = 0.2;
+            injector.duplicate_probability = 0.2;
+            injector.crash_probability = 0.3;
+            let messages: Vec<MessageFault> = (0..50).map(|_| injector.message_fault()).collect();
+            let faults = injector.due_faults(0, &[1, 2, 3], &[], &[10, 11]);
+            (messages, faults)
+        };
+
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42).0, run(43).0);
+    }
+
+    #[test]
+    fn test_always_drop() {
+        let mut injector = FaultInjector::new(7);
+        injector.drop_probability = 1.0;
+
+        assert_eq!(injector.message_fault(), MessageFault::Drop);
+    }
+}
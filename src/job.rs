@@ -0,0 +1,42 @@
+use crate::process::{Priority, Process};
+use crate::resource::Resources;
+
+// Processes that must start together or not at all (gang scheduling)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Job {
+    pub id: u32,
+    pub processes: Vec<Process>,
+}
+
+impl Job {
+    pub fn new(id: u32, processes: Vec<Process>) -> Self {
+        Job { id, processes }
+    }
+
+    // A job is as important as its most important process
+    pub fn priority(&self) -> Priority {
+        self.processes.iter().map(|p| p.priority).max().unwrap_or_default()
+    }
+
+    // Resources of the whole gang
+    pub fn needed_resources(&self) -> Resources {
+        self.processes.iter().map(|p| &p.needed_resources).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_priority_and_resources() {
+        let job = Job::new(1, vec![
+            Process::new(1, "Worker".to_string(), Resources::new(1_024, 1_000, 1)),
+            Process::with_priority(2, "Coordinator".to_string(), Resources::new(2_048, 1_000, 2), Priority::High),
+        ]);
+
+        assert_eq!(job.priority(), Priority::High);
+        assert_eq!(job.needed_resources(), Resources::new(3_072, 2_000, 3));
+        assert_eq!(Job::new(2, vec![]).priority(), Priority::Normal);
+    }
+}
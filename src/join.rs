@@ -0,0 +1,64 @@
+use std::fmt;
+
+// Why a node could not join the cluster
+#[derive(Clone, Debug, PartialEq)]
+pub enum JoinError {
+    // A member with the same id is already part of the cluster
+    DuplicateId { node_id: u32 },
+    // The id belongs to a node being recovered or to another pending join
+    ConflictingId { node_id: u32, reason: String },
+    // The members voted against admitting the node
+    Rejected { node_id: u32 },
+    // No active member has a quorum to sponsor the admission, e.g. during a partition
+    NoQuorum { node_id: u32 },
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::DuplicateId { node_id } => {
+                write!(f, "Node {} is already a member of the cluster", node_id)
+            }
+            JoinError::ConflictingId { node_id, reason } => {
+                write!(f, "Node id {} conflicts with {}", node_id, reason)
+            }
+            JoinError::Rejected { node_id } => {
+                write!(f, "Admission of node {} was rejected by the cluster", node_id)
+            }
+            JoinError::NoQuorum { node_id } => {
+                write!(f, "No member has a quorum to admit node {}", node_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JoinStatus {
+    Admitted,
+    // Votes are still on their way, the node is admitted once consensus is reached
+    Pending,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_error_display() {
+        assert_eq!(
+            JoinError::DuplicateId { node_id: 3 }.to_string(),
+            "Node 3 is already a member of the cluster"
+        );
+        assert_eq!(
+            JoinError::ConflictingId { node_id: 3, reason: "a node being recovered".to_string() }.to_string(),
+            "Node id 3 conflicts with a node being recovered"
+        );
+        assert_eq!(
+            JoinError::Rejected { node_id: 3 }.to_string(),
+            "Admission of node 3 was rejected by the cluster"
+        );
+        assert_eq!(JoinError::NoQuorum { node_id: 3 }.to_string(), "No member has a quorum to admit node 3");
+    }
+}
@@ -3,4 +3,23 @@ pub mod resource;
 pub mod session;
 pub mod message;
 pub mod process;
-pub mod task;
\ No newline at end of file
+pub mod task;
+pub mod clock;
+pub mod failure_detector;
+pub mod membership;
+pub mod recovery;
+pub mod network;
+pub mod fault;
+pub mod migration;
+pub mod drain;
+pub mod join;
+pub mod scheduler;
+pub mod affinity;
+pub mod job;
+pub mod deadline;
+pub mod tenant;
+pub mod backfill;
+pub mod stealing;
+pub mod rebalance;
+pub mod autoscale;
+pub mod runner;
\ No newline at end of file
@@ -5,7 +5,6 @@ use sistema_distribuido::process::Process;
 use tokio::sync::{Mutex};
 use std::sync::Arc;
 use tokio::time::{sleep, Duration};
-use std::collections::{HashMap, VecDeque};
 
 
 fn simulate_failure() {
@@ -27,61 +26,45 @@ fn simulate_failure() {
 
 #[tokio::main]
 async fn main() {
-    // Define total shared resources
-    let total_resources = Resources {
-        ram: 16 * 1024,       // 16 GB in MB
-        disk_space: 1_000_000, // 1 TB in MB
-        threads: 8,
-    };
+    // Create nodes, each machine with its own capacity (16 GB, 1 TB and 8 threads in total)
+    let node1 = Node::with_resources(1, Resources::new(4 * 1024, 250_000, 2));
+    let node2 = Node::with_resources(2, Resources::new(8 * 1024, 400_000, 4));
+    let node3 = Node::with_resources(3, Resources::new(4 * 1024, 350_000, 2));
 
-    // Create nodes
-    let node1 = Node::new(1);
-    let node2 = Node::new(2);
-    let node3 = Node::new(3);
-
-    // Initialize session with shared resources
-    let session = Session {
-        nodes: vec![node1, node2, node3],
-        processes: vec![],
-        total_resources: total_resources.clone(),
-        available_resources: total_resources,
-        pending_votes: HashMap::new(),
-        request_queue: VecDeque::new(),
-        deferred_replies: HashMap::new(),
-        replies_received: HashMap::new(),
-    };
+    // Initialize session, the cluster resources are the sum of the nodes'
+    let session = Session::new(vec![node1, node2, node3], vec![], Resources::new(0, 0, 0));
 
     // Wrap session in Arc and Mutex for thread-safe shared ownership
     let session = Arc::new(Mutex::new(session));
 
     // Create processes
-    let process1 = Process {
-        id: 1,
-        task: "Process A".to_string(),
-        needed_resources: Resources {
+    let process1 = Process::new(
+        1,
+        "Process A".to_string(),
+        Resources {
             ram: 4 * 1024,      // 4 GB
             disk_space: 200_000, // 200 GB
             threads: 2,
         },
-    };
-    let process2 = Process {
-        id: 2,
-        task: "Process B".to_string(),
-        needed_resources: Resources {
+    );
+    let process2 = Process::new(
+        2,
+        "Process B".to_string(),
+        Resources {
             ram: 8 * 1024,      // 8 GB
             disk_space: 300_000, // 300 GB
             threads: 4,
         },
-    };
-    let process3 = Process {
-        id: 3,
-        task: "Process C".to_string(),
-        needed_resources: Resources {
+    );
+    let process3 = Process::new(
+        3,
+        "Process C".to_string(),
+        Resources {
             ram: 2 * 1024,      // 2 GB
             disk_space: 100_000, // 100 GB
             threads: 1,
         },
-    };
+    );
 
     // Simulate nodes requesting resources concurrently
     let processes = vec![process1, process2, process3];
@@ -109,17 +92,19 @@ async fn main() {
             }
 
             // Access granted; allocate resources
-            if session_lock.allocate_resources(&process_clone.needed_resources) {
+            if session_lock.allocate_resources(node_id, &process_clone.needed_resources) {
                 println!("Node {} is executing process {}", node_id, process_clone.id);
 
                 // Simulate process execution
                 if let Some(node) = session_lock.nodes.iter().find(|n| n.id == node_id) {
                     // Call the async execute_process method
-                    node.execute_process(&process_clone).await;
+                    if let Err(error) = node.execute_process(&process_clone).await {
+                        eprintln!("Node {} couldn't run process {}: {}", node_id, process_clone.id, error);
+                    }
                 }
 
                 // Deallocate resources
-                session_lock.deallocate_resources(&process_clone.needed_resources);
+                session_lock.deallocate_resources(node_id, &process_clone.needed_resources);
             } else {
                 println!("Node {} failed to allocate resources for process {}", node_id, process_clone.id);
             }
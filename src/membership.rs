@@ -0,0 +1,428 @@
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemberStatus {
+    Alive,
+    Suspect,
+    Dead,
+    Left,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub node_id: u32,
+    pub status: MemberStatus,
+    pub incarnation: u64,
+    // Time of the last status change
+    pub since: u64,
+}
+
+// Membership updates piggybacked on SWIM messages
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MembershipEvent {
+    Join { node_id: u32, incarnation: u64 },
+    Alive { node_id: u32, incarnation: u64 },
+    Suspect { node_id: u32, incarnation: u64 },
+    Fail { node_id: u32, incarnation: u64 },
+    Leave { node_id: u32, incarnation: u64 },
+}
+
+impl MembershipEvent {
+    pub fn node_id(&self) -> u32 {
+        match self {
+            MembershipEvent::Join { node_id, .. }
+            | MembershipEvent::Alive { node_id, .. }
+            | MembershipEvent::Suspect { node_id, .. }
+            | MembershipEvent::Fail { node_id, .. }
+            | MembershipEvent::Leave { node_id, .. } => *node_id,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwimConfig {
+    // Number of members asked to probe a target indirectly
+    pub indirect_probes: usize,
+    // Time (ms) a member stays suspected before being declared dead
+    pub suspicion_timeout: u64,
+    // Number of times an event is piggybacked before being dropped
+    pub retransmit_limit: u32,
+    // Maximum number of events piggybacked on a single message
+    pub max_piggyback: usize,
+}
+
+impl Default for SwimConfig {
+    fn default() -> Self {
+        SwimConfig {
+            indirect_probes: 3,
+            suspicion_timeout: 5_000,
+            retransmit_limit: 6,
+            max_piggyback: 8,
+        }
+    }
+}
+
+// SWIM membership view kept by a single node
+#[derive(Clone, Debug, PartialEq)]
+pub struct Membership {
+    pub node_id: u32,
+    pub incarnation: u64,
+    pub config: SwimConfig,
+    // Other members of the cluster as seen by this node
+    pub members: HashMap<u32, Member>,
+    // Pending events and how many times each one has been sent
+    pub gossip: Vec<(MembershipEvent, u32)>,
+    probe_order: Vec<u32>,
+    rng: SmallRng,
+}
+
+impl Membership {
+    pub fn new(node_id: u32) -> Self {
+        Membership {
+            node_id,
+            incarnation: 0,
+            config: SwimConfig::default(),
+            members: HashMap::new(),
+            gossip: Vec::new(),
+            probe_order: Vec::new(),
+            rng: SmallRng::seed_from_u64(node_id as u64),
+        }
+    }
+
+    // Add a member known at startup, without gossiping it
+    pub fn add_seed(&mut self, node_id: u32, now: u64) {
+        if node_id != self.node_id {
+            self.members.entry(node_id).or_insert(Member {
+                node_id,
+                status: MemberStatus::Alive,
+                incarnation: 0,
+                since: now,
+            });
+        }
+    }
+
+    // Announce that this node joins the cluster
+    pub fn announce_join(&mut self) {
+        self.enqueue(MembershipEvent::Join { node_id: self.node_id, incarnation: self.incarnation });
+    }
+
+    // Rejoin after having been declared dead, superseding older reports,
+    // including the ones about the last incarnation known by the peers
+    pub fn rejoin(&mut self, known_incarnation: u64) {
+        self.incarnation = self.incarnation.max(known_incarnation) + 1;
+        self.members.clear();
+        self.gossip.clear();
+        self.probe_order.clear();
+        self.announce_join();
+    }
+
+    // Announce that this node leaves the cluster
+    pub fn announce_leave(&mut self) {
+        self.enqueue(MembershipEvent::Leave { node_id: self.node_id, incarnation: self.incarnation });
+    }
+
+    // A message from a member is proof that it is alive
+    pub fn record_contact(&mut self, node_id: u32, incarnation: u64, now: u64) {
+        if self.members.contains_key(&node_id) {
+            self.apply(MembershipEvent::Alive { node_id, incarnation }, now);
+        } else {
+            self.apply(MembershipEvent::Join { node_id, incarnation }, now);
+        }
+    }
+
+    pub fn status_of(&self, node_id: u32) -> Option<MemberStatus> {
+        self.members.get(&node_id).map(|m| m.status.clone())
+    }
+
+    // Members considered part of the cluster (alive or suspected), sorted by id
+    pub fn live_members(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .members
+            .values()
+            .filter(|m| matches!(m.status, MemberStatus::Alive | MemberStatus::Suspect))
+            .map(|m| m.node_id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    // Pick the next member to probe, walking a shuffled list round-robin
+    pub fn next_probe_target(&mut self) -> Option<u32> {
+        loop {
+            if self.probe_order.is_empty() {
+                self.probe_order = self.live_members();
+                if self.probe_order.is_empty() {
+                    return None;
+                }
+                self.probe_order.shuffle(&mut self.rng);
+            }
+            let candidate = self.probe_order.remove(0);
+            if self.live_members().contains(&candidate) {
+                return Some(candidate);
+            }
+        }
+    }
+
+    // Pick up to `indirect_probes` random live members other than the target
+    pub fn indirect_probe_helpers(&mut self, target: u32) -> Vec<u32> {
+        let mut candidates: Vec<u32> = self
+            .live_members()
+            .into_iter()
+            .filter(|&id| id != target)
+            .collect();
+        candidates.shuffle(&mut self.rng);
+        candidates.truncate(self.config.indirect_probes);
+        candidates
+    }
+
+    // Events to piggyback on the next outgoing message
+    pub fn piggyback(&mut self) -> Vec<MembershipEvent> {
+        // Least disseminated events first
+        self.gossip.sort_by_key(|(_, sent)| *sent);
+        let mut events = Vec::new();
+        for (event, sent) in self.gossip.iter_mut().take(self.config.max_piggyback) {
+            *sent += 1;
+            events.push(event.clone());
+        }
+        let limit = self.config.retransmit_limit;
+        self.gossip.retain(|(_, sent)| *sent < limit);
+        events
+    }
+
+    // Mark a member as suspected after failed direct and indirect probes
+    pub fn suspect(&mut self, node_id: u32, now: u64) {
+        if let Some(member) = self.members.get(&node_id) {
+            let event = MembershipEvent::Suspect { node_id, incarnation: member.incarnation };
+            self.apply(event, now);
+        }
+    }
+
+    // Declare dead every member suspected for longer than the suspicion timeout
+    pub fn expire_suspects(&mut self, now: u64) {
+        let expired: Vec<(u32, u64)> = self
+            .members
+            .values()
+            .filter(|m| m.status == MemberStatus::Suspect && now.saturating_sub(m.since) >= self.config.suspicion_timeout)
+            .map(|m| (m.node_id, m.incarnation))
+            .collect();
+
+        for (node_id, incarnation) in expired {
+            println!("Node {}: member {} confirmed dead", self.node_id, node_id);
+            self.apply(MembershipEvent::Fail { node_id, incarnation }, now);
+        }
+    }
+
+    pub fn apply_all(&mut self, events: Vec<MembershipEvent>, now: u64) {
+        for event in events {
+            self.apply(event, now);
+        }
+    }
+
+    // Apply an event following the SWIM precedence rules.
+    // Returns true if the view changed (the event is then gossiped further).
+    pub fn apply(&mut self, event: MembershipEvent, now: u64) -> bool {
+        let node_id = event.node_id();
+
+        if node_id == self.node_id {
+            return self.apply_about_self(event);
+        }
+
+        let changed = match (&event, self.members.get(&node_id)) {
+            (MembershipEvent::Join { incarnation, .. }, None)
+            | (MembershipEvent::Alive { incarnation, .. }, None) => {
+                self.set_member(node_id, MemberStatus::Alive, *incarnation, now);
+                true
+            }
+            (MembershipEvent::Join { incarnation, .. }, Some(member))
+            | (MembershipEvent::Alive { incarnation, .. }, Some(member)) => {
+                // A higher incarnation overrides any previous state (refutation or rejoin)
+                if *incarnation > member.incarnation {
+                    self.set_member(node_id, MemberStatus::Alive, *incarnation, now);
+                    true
+                } else {
+                    false
+                }
+            }
+            (MembershipEvent::Suspect { incarnation, .. }, Some(member)) => {
+                let overrides = match member.status {
+                    MemberStatus::Alive => *incarnation >= member.incarnation,
+                    MemberStatus::Suspect => *incarnation > member.incarnation,
+                    MemberStatus::Dead | MemberStatus::Left => false,
+                };
+                if overrides {
+                    self.set_member(node_id, MemberStatus::Suspect, *incarnation, now);
+                }
+                overrides
+            }
+            (MembershipEvent::Fail { incarnation, .. }, Some(member)) => {
+                if matches!(member.status, MemberStatus::Alive | MemberStatus::Suspect) && *incarnation >= member.incarnation {
+                    self.set_member(node_id, MemberStatus::Dead, *incarnation, now);
+                    true
+                } else {
+                    false
+                }
+            }
+            (MembershipEvent::Leave { incarnation, .. }, Some(member)) => {
+                // A departure from before a rejoin is stale
+                if member.status != MemberStatus::Left && *incarnation >= member.incarnation {
+                    self.set_member(node_id, MemberStatus::Left, *incarnation, now);
+                    true
+                } else {
+                    false
+                }
+            }
+            // Suspicion, failure or departure of an unknown member
+            (_, None) => false,
+        };
+
+        if changed {
+            self.enqueue(event);
+        }
+        changed
+    }
+
+    // Refute suspicion or failure reports about this node by bumping the incarnation
+    fn apply_about_self(&mut self, event: MembershipEvent) -> bool {
+        match event {
+            MembershipEvent::Suspect { incarnation, .. } | MembershipEvent::Fail { incarnation, .. } => {
+                if incarnation >= self.incarnation {
+                    self.incarnation = incarnation + 1;
+                    println!("Node {}: refuting suspicion with incarnation {}", self.node_id, self.incarnation);
+                    self.enqueue(MembershipEvent::Alive { node_id: self.node_id, incarnation: self.incarnation });
+                    return true;
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn set_member(&mut self, node_id: u32, status: MemberStatus, incarnation: u64, now: u64) {
+        self.members.insert(node_id, Member { node_id, status, incarnation, since: now });
+    }
+
+    fn enqueue(&mut self, event: MembershipEvent) {
+        // A newer event about a member replaces the older one
+        let node_id = event.node_id();
+        self.gossip.retain(|(e, _)| e.node_id() != node_id);
+        self.gossip.push((event, 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_and_alive_events() {
+        let mut membership = Membership::new(1);
+
+        assert!(membership.apply(MembershipEvent::Join { node_id: 2, incarnation: 0 }, 0));
+        assert_eq!(membership.status_of(2), Some(MemberStatus::Alive));
+        // Duplicated event does not change the view
+        assert!(!membership.apply(MembershipEvent::Alive { node_id: 2, incarnation: 0 }, 0));
+        assert_eq!(membership.live_members(), vec![2]);
+    }
+
+    #[test]
+    fn test_suspicion_refuted_by_higher_incarnation() {
+        let mut membership = Membership::new(1);
+        membership.add_seed(2, 0);
+
+        membership.suspect(2, 100);
+        assert_eq!(membership.status_of(2), Some(MemberStatus::Suspect));
+
+        // Alive with the same incarnation does not clear the suspicion
+        assert!(!membership.apply(MembershipEvent::Alive { node_id: 2, incarnation: 0 }, 200));
+        assert!(membership.apply(MembershipEvent::Alive { node_id: 2, incarnation: 1 }, 200));
+        assert_eq!(membership.status_of(2), Some(MemberStatus::Alive));
+    }
+
+    #[test]
+    fn test_node_refutes_suspicion_about_itself() {
+        let mut membership = Membership::new(1);
+
+        assert!(membership.apply(MembershipEvent::Suspect { node_id: 1, incarnation: 0 }, 0));
+        assert_eq!(membership.incarnation, 1);
+        assert_eq!(
+            membership.piggyback(),
+            vec![MembershipEvent::Alive { node_id: 1, incarnation: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_expire_suspects() {
+        let mut membership = Membership::new(1);
+        membership.add_seed(2, 0);
+        membership.suspect(2, 1_000);
+
+        membership.expire_suspects(2_000);
+        assert_eq!(membership.status_of(2), Some(MemberStatus::Suspect));
+
+        membership.expire_suspects(1_000 + membership.config.suspicion_timeout);
+        assert_eq!(membership.status_of(2), Some(MemberStatus::Dead));
+        assert!(membership.live_members().is_empty());
+    }
+
+    #[test]
+    fn test_dead_member_rejoins_with_higher_incarnation() {
+        let mut membership = Membership::new(1);
+        membership.add_seed(2, 0);
+        membership.apply(MembershipEvent::Fail { node_id: 2, incarnation: 0 }, 0);
+
+        assert!(!membership.apply(MembershipEvent::Join { node_id: 2, incarnation: 0 }, 10));
+        assert!(membership.apply(MembershipEvent::Join { node_id: 2, incarnation: 1 }, 10));
+        assert_eq!(membership.status_of(2), Some(MemberStatus::Alive));
+    }
+
+    #[test]
+    fn test_rejoin_supersedes_known_incarnation() {
+        let mut membership = Membership::new(2);
+        membership.add_seed(1, 0);
+        membership.rejoin(3);
+
+        assert_eq!(membership.incarnation, 4);
+        assert!(membership.members.is_empty());
+        assert_eq!(membership.piggyback(), vec![MembershipEvent::Join { node_id: 2, incarnation: 4 }]);
+    }
+
+    #[test]
+    fn test_stale_leave_after_rejoin() {
+        let mut membership = Membership::new(1);
+        membership.add_seed(2, 0);
+        assert!(membership.apply(MembershipEvent::Leave { node_id: 2, incarnation: 0 }, 0));
+        assert!(membership.apply(MembershipEvent::Join { node_id: 2, incarnation: 1 }, 10));
+
+        // The old departure arrives again late, the rejoined member stays
+        assert!(!membership.apply(MembershipEvent::Leave { node_id: 2, incarnation: 0 }, 20));
+        assert_eq!(membership.status_of(2), Some(MemberStatus::Alive));
+        assert!(membership.apply(MembershipEvent::Leave { node_id: 2, incarnation: 1 }, 30));
+        assert_eq!(membership.status_of(2), Some(MemberStatus::Left));
+    }
+
+    #[test]
+    fn test_piggyback_retransmit_limit() {
+        let mut membership = Membership::new(1);
+        membership.config.retransmit_limit = 2;
+        membership.apply(MembershipEvent::Join { node_id: 2, incarnation: 0 }, 0);
+
+        assert_eq!(membership.piggyback().len(), 1);
+        assert_eq!(membership.piggyback().len(), 1);
+        assert!(membership.piggyback().is_empty());
+    }
+
+    #[test]
+    fn test_probe_target_visits_every_member() {
+        let mut membership = Membership::new(1);
+        for id in 2..=5 {
+            membership.add_seed(id, 0);
+        }
+
+        let mut probed: Vec<u32> = (0..4).map(|_| membership.next_probe_target().unwrap()).collect();
+        probed.sort();
+        assert_eq!(probed, vec![2, 3, 4, 5]);
+    }
+}
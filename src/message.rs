@@ -8,11 +8,49 @@ pub enum Vote {
 pub enum Action {
     ProcessFailure { node_id: u32, reason: String },
     NodeFailure { node_id: u32, reason: String },
-    RedistributeProcess { process_id: u32 },
+    // Move a process to the given node, or to the one chosen by the scheduler
+    RedistributeProcess { process_id: u32, target_node_id: Option<u32> },
+    MigrateProcess { process_id: u32, target_node_id: u32 },
+    // Move the node's processes away within `timeout` ms, then remove it
+    DrainNode { node_id: u32, timeout: u64 },
+    // Admit a node that asked to join the cluster
+    AdmitNode { node_id: u32 },
+}
+
+// Result of executing an action, reported back to its proposer
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActionOutcome {
+    Redistributed { process_id: u32, from_node_id: Option<u32>, to_node_id: u32 },
+    // No other node can host the process, it stays where it was
+    NoCapacity { process_id: u32 },
+    ProcessNotFound { process_id: u32 },
+    Migrated { process_id: u32, from_node_id: u32, to_node_id: u32 },
+    MigrationAborted { process_id: u32, reason: String },
+    // Processes migrated and processes requeued once the deadline passed
+    Drained { node_id: u32, migrated: Vec<u32>, requeued: Vec<u32> },
+    NodeNotFound { node_id: u32 },
 }
 
 #[derive(Clone, Debug)]
 pub struct Request {
     pub from_node_id: u32,
     pub timestamp: u64,
+}
+
+// Messages exchanged between nodes through the simulated network
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    // A vote on a proposed action, sent to the proposer
+    Vote { action: Action, vote: Vote },
+    // Ricart-Agrawala reply granting access to the shared resource
+    Reply,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Envelope {
+    pub from_node_id: u32,
+    pub to_node_id: u32,
+    pub message: Message,
+    // Time at which the message arrives
+    pub deliver_at: u64,
 }
\ No newline at end of file
@@ -0,0 +1,138 @@
+use crate::resource::Resources;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MigrationPhase {
+    // State is copied while the process keeps running on the source
+    PreCopy,
+    // Process paused on the source, remaining dirty state is copied
+    Paused,
+    // Process moves to the target and resumes there
+    CutOver,
+    // Source releases the resources the process used
+    Cleanup,
+    Completed,
+    Aborted { reason: String },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrationConfig {
+    // Maximum number of pre-copy rounds before forcing the pause
+    pub max_precopy_rounds: u32,
+    // Pause as soon as the dirty state is this small (pages)
+    pub stop_copy_threshold: u64,
+    // Fraction of the copied pages dirtied again by the running process during a round
+    pub dirty_ratio: f64,
+}
+
+impl Default for MigrationConfig {
+    fn default() -> Self {
+        MigrationConfig {
+            max_precopy_rounds: 5,
+            stop_copy_threshold: 64,
+            dirty_ratio: 0.25,
+        }
+    }
+}
+
+// Live migration of one process between two nodes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+    pub process_id: u32,
+    pub source_node_id: u32,
+    pub target_node_id: u32,
+    // Node that proposed the migration, receives the outcome
+    pub proposer_id: u32,
+    pub phase: MigrationPhase,
+    pub precopy_rounds: u32,
+    // Pages copied so far and pages still to copy
+    pub copied_pages: u64,
+    pub dirty_pages: u64,
+    // Pages copied while the process was paused
+    pub downtime_pages: u64,
+    // Resources reserved on the target for the process
+    pub reserved: Resources,
+}
+
+impl Migration {
+    pub fn new(process_id: u32, source_node_id: u32, target_node_id: u32, proposer_id: u32, pages: u64) -> Self {
+        Migration {
+            process_id,
+            source_node_id,
+            target_node_id,
+            proposer_id,
+            phase: MigrationPhase::PreCopy,
+            precopy_rounds: 0,
+            copied_pages: 0,
+            dirty_pages: pages,
+            downtime_pages: 0,
+            reserved: Resources::new(0, 0, 0),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.phase, MigrationPhase::Completed | MigrationPhase::Aborted { .. })
+    }
+
+    // Run one pre-copy round. Returns true once the process should be paused.
+    pub fn precopy_round(&mut self, config: &MigrationConfig) -> bool {
+        let copied = self.dirty_pages;
+        self.copied_pages += copied;
+        self.precopy_rounds += 1;
+        // The process keeps running and dirties part of what was just copied
+        self.dirty_pages = (copied as f64 * config.dirty_ratio) as u64;
+
+        self.dirty_pages <= config.stop_copy_threshold || self.precopy_rounds >= config.max_precopy_rounds
+    }
+
+    // Copy the remaining dirty pages while the process is paused
+    pub fn stop_and_copy(&mut self) {
+        self.downtime_pages = self.dirty_pages;
+        self.copied_pages += self.dirty_pages;
+        self.dirty_pages = 0;
+    }
+
+    pub fn abort(&mut self, reason: String) {
+        self.phase = MigrationPhase::Aborted { reason };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precopy_converges() {
+        let config = MigrationConfig::default();
+        let mut migration = Migration::new(1, 1, 2, 1, 4_096);
+
+        assert!(!migration.precopy_round(&config)); // 1024 dirty
+        assert!(!migration.precopy_round(&config)); // 256 dirty
+        assert!(migration.precopy_round(&config)); // 64 dirty
+        assert_eq!(migration.precopy_rounds, 3);
+        assert_eq!(migration.dirty_pages, 64);
+
+        migration.stop_and_copy();
+        assert_eq!(migration.downtime_pages, 64);
+        assert_eq!(migration.copied_pages, 4_096 + 1_024 + 256 + 64);
+    }
+
+    #[test]
+    fn test_precopy_round_limit() {
+        let config = MigrationConfig { dirty_ratio: 1.0, max_precopy_rounds: 2, ..MigrationConfig::default() };
+        let mut migration = Migration::new(1, 1, 2, 1, 4_096);
+
+        // A process dirtying everything never converges, the limit forces the pause
+        assert!(!migration.precopy_round(&config));
+        assert!(migration.precopy_round(&config));
+        assert_eq!(migration.dirty_pages, 4_096);
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut migration = Migration::new(1, 1, 2, 1, 10);
+        assert!(!migration.is_finished());
+
+        migration.abort("Target unreachable".to_string());
+        assert!(migration.is_finished());
+    }
+}
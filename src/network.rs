@@ -0,0 +1,118 @@
+use crate::message::Envelope;
+use std::collections::HashSet;
+
+// Simulated network connecting the nodes of a session
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Network {
+    // Groups of nodes that can only reach each other. Empty when there is no partition.
+    pub partitions: Vec<HashSet<u32>>,
+    // Messages sent but not delivered yet, in sending order
+    pub in_flight: Vec<Envelope>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Network {
+            partitions: Vec::new(),
+            in_flight: Vec::new(),
+        }
+    }
+
+    // Queue a message for later delivery
+    pub fn enqueue(&mut self, envelope: Envelope) {
+        self.in_flight.push(envelope);
+    }
+
+    // Remove and return the messages due at the given time, keeping their sending order
+    pub fn take_due(&mut self, now: u64) -> Vec<Envelope> {
+        let (due, pending): (Vec<Envelope>, Vec<Envelope>) =
+            self.in_flight.drain(..).partition(|e| e.deliver_at <= now);
+        self.in_flight = pending;
+        due
+    }
+
+    // Split the network into isolated groups.
+    // Nodes not listed in any group are isolated from everyone else.
+    pub fn partition(&mut self, groups: Vec<Vec<u32>>) {
+        println!("Network partitioned into {:?}", groups);
+        self.partitions = groups
+            .into_iter()
+            .map(|group| group.into_iter().collect())
+            .collect();
+    }
+
+    // Restore full connectivity
+    pub fn heal(&mut self) {
+        println!("Network partition healed");
+        self.partitions.clear();
+    }
+
+    pub fn is_partitioned(&self) -> bool {
+        !self.partitions.is_empty()
+    }
+
+    // Whether a message sent from one node can arrive to another
+    pub fn can_reach(&self, from_node_id: u32, to_node_id: u32) -> bool {
+        if from_node_id == to_node_id || !self.is_partitioned() {
+            return true;
+        }
+        self.partitions
+            .iter()
+            .any(|group| group.contains(&from_node_id) && group.contains(&to_node_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+
+    #[test]
+    fn test_healthy_network_reaches_everyone() {
+        let network = Network::new();
+
+        assert!(!network.is_partitioned());
+        assert!(network.can_reach(1, 2));
+        assert!(network.can_reach(2, 1));
+    }
+
+    #[test]
+    fn test_partition_and_heal() {
+        let mut network = Network::new();
+        network.partition(vec![vec![1, 2, 3], vec![4, 5]]);
+
+        assert!(network.can_reach(1, 3));
+        assert!(network.can_reach(5, 4));
+        assert!(!network.can_reach(1, 4));
+        assert!(!network.can_reach(5, 2));
+
+        network.heal();
+        assert!(network.can_reach(1, 4));
+    }
+
+    #[test]
+    fn test_take_due_messages() {
+        let mut network = Network::new();
+        for (to_node_id, deliver_at) in [(1, 100), (2, 50), (3, 200)] {
+            network.enqueue(Envelope {
+                from_node_id: 0,
+                to_node_id,
+                message: Message::Reply,
+                deliver_at,
+            });
+        }
+
+        let due: Vec<u32> = network.take_due(100).iter().map(|e| e.to_node_id).collect();
+        assert_eq!(due, vec![1, 2]);
+        assert_eq!(network.in_flight.len(), 1);
+    }
+
+    #[test]
+    fn test_unlisted_node_is_isolated() {
+        let mut network = Network::new();
+        network.partition(vec![vec![1, 2]]);
+
+        assert!(!network.can_reach(3, 1));
+        assert!(network.can_reach(3, 3));
+    }
+}
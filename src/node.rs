@@ -1,7 +1,12 @@
-use crate::process::Process;
+use crate::process::{Process, ProcessOutput, ProcessState, TransitionError};
+use crate::runner;
 use crate::session::Session;
-use crate::message::{Vote, Action};
-use std::collections::{HashMap};
+use crate::message::{Vote, Action, ActionOutcome};
+use crate::failure_detector::PhiAccrualDetector;
+use crate::membership::Membership;
+use crate::resource::Resources;
+use std::collections::{HashMap, VecDeque};
+use std::io;
 use tokio::time::{sleep, Duration};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -9,6 +14,8 @@ pub enum NodeStatus {
     Active,
     Halted,
     Recovering,
+    // Unschedulable, its processes are being moved away before removal
+    Draining,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -16,24 +23,72 @@ pub struct Node {
     pub id: u32,
     // List of active processes
     pub active_processes: Vec<Process>,
+    // Processes dispatched to the node that haven't started yet, oldest first
+    pub local_queue: VecDeque<Process>,
     pub timestamp: u64,
     pub status: NodeStatus,
+    // Capacity of the machine and what is left of it after placing its processes
+    pub total_resources: Resources,
+    pub available_resources: Resources,
+    // Properties of the machine (zone, disk type...) used by placement constraints
+    pub labels: HashMap<String, String>,
     pub last_heartbeat: u64,
     pub known_actions: HashMap<Action, bool>,
+    // Heartbeat based suspicion of the other nodes
+    pub failure_detector: PhiAccrualDetector,
+    // This node's own view of the cluster (SWIM)
+    pub membership: Membership,
+    // Recovery: whether the node has caught up with the cluster and sent a heartbeat since it was reinstalled
+    pub caught_up: bool,
+    pub heartbeat_since_reinstall: bool,
+    // Outcomes of the actions proposed by this node
+    pub action_outcomes: Vec<(Action, ActionOutcome)>,
 }
 
 impl Node {
     pub fn new(id: u32) -> Self {
+        Node::with_resources(id, Resources::new(0, 0, 0))
+    }
+
+    pub fn with_resources(id: u32, total_resources: Resources) -> Self {
         Node {
             id,
             active_processes: Vec::new(),
+            local_queue: VecDeque::new(),
             timestamp: 0,
             status: NodeStatus::Active,
+            total_resources: total_resources.clone(),
+            available_resources: total_resources,
+            labels: HashMap::new(),
             last_heartbeat: 0,
             known_actions: HashMap::new(),
+            failure_detector: PhiAccrualDetector::default(),
+            membership: Membership::new(id),
+            caught_up: false,
+            heartbeat_since_reinstall: false,
+            action_outcomes: Vec::new(),
         }
     }
 
+    // Whether the node has room left for the given resources
+    pub fn can_host(&self, needed_resources: &Resources) -> bool {
+        self.available_resources.can_allocate(needed_resources)
+    }
+
+    // Nothing queued locally and room left for more work
+    pub fn is_idle(&self) -> bool {
+        let available = &self.available_resources;
+        self.local_queue.is_empty() && available.ram > 0 && available.disk_space > 0 && available.threads > 0
+    }
+
+    pub fn allocate(&mut self, needed_resources: &Resources) -> bool {
+        self.available_resources.allocate(needed_resources)
+    }
+
+    pub fn deallocate(&mut self, used_resources: &Resources) {
+        self.available_resources.deallocate(used_resources);
+    }
+
     // Node receives a proposal and decides whether to vote
     pub fn receive_proposal(&mut self, action: Action) -> Vote {
         println!("Node {} received proposal for action {:?}", self.id, action);
@@ -61,8 +116,18 @@ impl Node {
                     Vote::Approve
                 }
             }
-            Action::NodeFailure { reason, .. } => {
-                if reason.contains("hardware") {
+            Action::NodeFailure { node_id, reason } => {
+                // Don't vote a node out while its heartbeats still look healthy
+                if node_id == self.id {
+                    println!("Node {}: Rejecting action, this node is still alive.", self.id);
+                    Vote::Reject
+                } else if let Some(false) = self.failure_detector.is_suspected(node_id) {
+                    println!(
+                        "Node {}: Rejecting action, node {} is not suspected (phi = {:.2}).",
+                        self.id, node_id, self.suspicion_level(node_id).unwrap_or(0.0)
+                    );
+                    Vote::Reject
+                } else if reason.contains("hardware") {
                     println!("Node {}: Rejecting action due to hardware issue.", self.id);
                     Vote::Reject
                 } else {
@@ -70,6 +135,16 @@ impl Node {
                     Vote::Approve
                 }
             }
+            Action::AdmitNode { node_id } => {
+                // The id must not belong to a member this node still sees alive
+                if node_id == self.id || self.membership.live_members().contains(&node_id) {
+                    println!("Node {}: Rejecting admission, node id {} is in use.", self.id, node_id);
+                    Vote::Reject
+                } else {
+                    println!("Node {}: Approving admission of node {}.", self.id, node_id);
+                    Vote::Approve
+                }
+            }
             _ => {
                 println!("Node {}: Approving unknown action.", self.id);
                 Vote::Approve
@@ -77,6 +152,22 @@ impl Node {
         }
     }
 
+    // Record a heartbeat received from another node
+    pub fn observe_heartbeat(&mut self, from_node_id: u32, arrival: u64) {
+        self.failure_detector.heartbeat(from_node_id, arrival);
+    }
+
+    // Suspicion level (phi) this node has about another node
+    pub fn suspicion_level(&self, node_id: u32) -> Option<f64> {
+        self.failure_detector.phi(node_id)
+    }
+
+    // Outcome of an action this node proposed
+    pub fn receive_outcome(&mut self, action: Action, outcome: ActionOutcome) {
+        println!("Node {} received outcome {:?} for action {:?}", self.id, outcome, action);
+        self.action_outcomes.push((action, outcome));
+    }
+
     pub fn propose_action(&mut self, session: &mut Session, action: Action) {
         println!("Node {} is proposing action {:?}", self.id, action);
 
@@ -84,6 +175,34 @@ impl Node {
         session.initiate_voting(self.id, action);
     } 
 
+    // Run a process on this node, its resources are allocated by the caller
+    pub fn start_process(&mut self, mut process: Process, at: u64) -> Result<(), TransitionError> {
+        process.transition(ProcessState::Running, at)?;
+        process.started_at = Some(at);
+        self.active_processes.push(process);
+        Ok(())
+    }
+
+    // Take a running process off the node, moving it to `to` (a final state, or Pending when requeued).
+    // Ok(None) when the process doesn't run here.
+    pub fn stop_process(&mut self, process_id: u32, to: ProcessState, at: u64) -> Result<Option<Process>, TransitionError> {
+        let index = match self.active_processes.iter().position(|p| p.id == process_id) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        self.active_processes[index].transition(to, at)?;
+        Ok(Some(self.active_processes.remove(index)))
+    }
+
+    // Forget the copies of processes that the cluster took over meanwhile.
+    // Returns the ones that were running, their resources are released by the caller.
+    pub fn drop_processes(&mut self, process_ids: &[u32]) -> Vec<Process> {
+        self.local_queue.retain(|p| !process_ids.contains(&p.id));
+        let (dropped, kept) = self.active_processes.drain(..).partition(|p| process_ids.contains(&p.id));
+        self.active_processes = kept;
+        dropped
+    }
+
     // Handle process failure and deallocate resources
     pub fn handle_process_failure(&mut self, process_id: u32, reason: String) {
         if let Some(pos) = self.active_processes.iter().position(|p| p.id == process_id) {
@@ -131,12 +250,19 @@ impl Node {
         }
     }   
 
-    // Simulate process execution
-    pub async fn execute_process(&self, process: &Process) {
+    // Run the process' command, or simulate some work when it has none
+    pub async fn execute_process(&self, process: &Process) -> io::Result<ProcessOutput> {
         println!("Node {} is processing {}", self.id, process.task);
-        // Simulate some work
-        sleep(Duration::from_secs(1)).await;
-        println!("Node {} completed process {}", self.id, process.id);
+        let output = match &process.command {
+            Some(command) => runner::execute(command).await?,
+            None => {
+                // Simulate some work
+                sleep(Duration::from_secs(1)).await;
+                ProcessOutput { exit_code: Some(0), success: true, stdout: String::new(), stderr: String::new() }
+            }
+        };
+        println!("Node {} completed process {} (exit code {:?})", self.id, process.id, output.exit_code);
+        Ok(output)
     }
 
     pub fn halt(&mut self, session: &mut Session, reason: String) {
@@ -144,10 +270,10 @@ impl Node {
         self.handle_failure(session, reason);
     }
     
-    pub fn print_status(&self, session: &Session) {
+    pub fn print_status(&self) {
         println!(
             "Node {} - Available Resources: {}, Active Processes: {}",
-            self.id, session.available_resources, self.active_processes.len()
+            self.id, self.available_resources, self.active_processes.len()
         );
     }
 
@@ -156,7 +282,7 @@ impl Node {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::process::Process;
+    use crate::process::{CommandSpec, Process};
     use crate::resource::Resources;
     use crate::message::{Action, Vote};
     use std::collections::HashMap;
@@ -217,6 +343,37 @@ mod tests {
         assert!(node.known_actions.contains_key(&action));
     }
 
+    #[test]
+    fn test_is_idle() {
+        let mut node = Node::with_resources(1, Resources::new(4_096, 10_000, 2));
+        assert!(node.is_idle());
+
+        // Saturated, even with nothing queued
+        node.allocate(&Resources::new(4_096, 1_000, 2));
+        assert!(!node.is_idle());
+
+        node.deallocate(&Resources::new(4_096, 1_000, 2));
+        node.local_queue.push_back(Process::new(1, "Queued".to_string(), Resources::new(1, 1, 1)));
+        assert!(!node.is_idle());
+    }
+
+    #[test]
+    fn test_start_and_stop_process() {
+        let mut node = Node::with_resources(1, Resources::new(4_096, 10_000, 2));
+        let process = Process::new(7, "Lifecycle".to_string(), Resources::new(1_024, 1_000, 1));
+
+        node.start_process(process, 100).unwrap();
+        assert_eq!(node.active_processes[0].state, ProcessState::Running);
+        assert_eq!(node.active_processes[0].started_at, Some(100));
+
+        // A running process can't go back to Scheduled
+        assert!(node.stop_process(7, ProcessState::Scheduled, 200).is_err());
+        let stopped = node.stop_process(7, ProcessState::Succeeded, 200).unwrap().unwrap();
+        assert_eq!(stopped.entered_at(ProcessState::Succeeded), Some(200));
+        assert!(node.active_processes.is_empty());
+        assert_eq!(node.stop_process(7, ProcessState::Failed, 300), Ok(None));
+    }
+
     #[test]
     fn test_handle_process_failure_existing_process() {
         let mut node = Node::new(1);
@@ -258,6 +415,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_vote_node_failure_uses_suspicion_level() {
+        let mut node = Node::new(1);
+        for t in 1..=10 {
+            node.observe_heartbeat(2, t * 1_000);
+            node.observe_heartbeat(3, t * 1_000);
+        }
+        // Node 2 keeps sending heartbeats, node 3 goes silent
+        for t in 11..=20 {
+            node.observe_heartbeat(2, t * 1_000);
+        }
+
+        let healthy = Action::NodeFailure { node_id: 2, reason: "Timeout".to_string() };
+        let silent = Action::NodeFailure { node_id: 3, reason: "Timeout".to_string() };
+
+        assert_eq!(node.vote(healthy), Vote::Reject);
+        assert_eq!(node.vote(silent), Vote::Approve);
+        assert!(node.suspicion_level(3).unwrap() > node.suspicion_level(2).unwrap());
+    }
+
+    #[test]
+    fn test_vote_node_failure_without_heartbeats() {
+        let mut node = Node::new(1);
+        let action = Action::NodeFailure { node_id: 2, reason: "Timeout".to_string() };
+
+        // No heartbeat history: fall back to the reason based validation
+        assert_eq!(node.vote(action), Vote::Approve);
+    }
+
     #[tokio::test]
     async fn test_execute_process() {
         let node = Node::new(1);
@@ -267,8 +453,20 @@ mod tests {
             Resources::new(2048, 200_000, 4),
         );
 
-        node.execute_process(&process).await;
+        // Without a command the execution is only simulated
+        let output = node.execute_process(&process).await.unwrap();
+        assert!(output.success);
+        assert!(output.stdout.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_process_command() {
+        let node = Node::new(1);
+        let mut process = Process::new(102, "Echo".to_string(), Resources::new(1, 1, 1));
+        process.command = Some(CommandSpec::new("echo", &["hello"]));
 
-        // Since execute_process only simulates execution with sleep, there's no state change to assert
+        let output = node.execute_process(&process).await.unwrap();
+        assert_eq!(output.stdout, "hello\n");
     }
 }
\ No newline at end of file
@@ -1,15 +1,166 @@
+use crate::affinity::Constraint;
 use crate::resource::Resources;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+// Priority classes, from lowest to highest
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    BestEffort,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+// Operating system command run by the process (see `runner.rs`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    // Added to the environment inherited from the runner
+    pub env: HashMap<String, String>,
+    // Runner's current directory when None
+    pub working_dir: Option<PathBuf>,
+}
+
+impl CommandSpec {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        CommandSpec {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            env: HashMap::new(),
+            working_dir: None,
+        }
+    }
+}
+
+// What a command left behind when it exited
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessOutput {
+    // None when killed by a signal
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// Lifecycle of a process
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ProcessState {
+    // Waiting in the cluster queue
+    #[default]
+    Pending,
+    // Assigned to a node, waiting in its local queue
+    Scheduled,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl ProcessState {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, ProcessState::Succeeded | ProcessState::Failed | ProcessState::Cancelled)
+    }
+
+    // Scheduled and running processes go back to Pending when requeued (preemption, node failure...)
+    pub fn can_transition_to(&self, next: ProcessState) -> bool {
+        use ProcessState::*;
+        matches!(
+            (self, next),
+            (Pending, Scheduled | Running | Failed | Cancelled)
+                | (Scheduled, Pending | Running | Failed | Cancelled)
+                | (Running, Pending | Succeeded | Failed | Cancelled)
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransitionError {
+    pub process_id: u32,
+    pub from: ProcessState,
+    pub to: ProcessState,
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Process {} can't go from {:?} to {:?}", self.process_id, self.from, self.to)
+    }
+}
+
+impl std::error::Error for TransitionError {}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Process {
     pub id: u32,
     pub task: String,
     pub needed_resources: Resources,
+    // Paused during a migration cutover, the process isn't restarted when it resumes on the target
+    pub paused: bool,
+    // Higher priority processes are scheduled first and may preempt lower priority ones
+    pub priority: Priority,
+    // Placement constraints enforced by the scheduler
+    pub constraints: Vec<Constraint>,
+    // Time (ms, session clock) by which the process should be done, and how long it is expected to run
+    pub deadline: Option<u64>,
+    pub estimated_duration: Option<u64>,
+    // When it was last placed on a node
+    pub started_at: Option<u64>,
+    // Team owning the process, its usage counts against the team's quota
+    pub tenant: Option<String>,
+    pub state: ProcessState,
+    // Every state change with its time, oldest first
+    pub transitions: Vec<(ProcessState, u64)>,
+    // Command to run, the execution is only simulated when None
+    pub command: Option<CommandSpec>,
+    // Set once the command has exited
+    pub output: Option<ProcessOutput>,
 }
 
 impl Process {
     pub fn new(id: u32, task: String, needed_resources: Resources) -> Self {
-        Process { id, task, needed_resources }
+        Process::with_priority(id, task, needed_resources, Priority::Normal)
+    }
+
+    pub fn with_priority(id: u32, task: String, needed_resources: Resources, priority: Priority) -> Self {
+        Process {
+            id,
+            task,
+            needed_resources,
+            paused: false,
+            priority,
+            constraints: Vec::new(),
+            deadline: None,
+            estimated_duration: None,
+            started_at: None,
+            tenant: None,
+            state: ProcessState::Pending,
+            transitions: Vec::new(),
+            command: None,
+            output: None,
+        }
+    }
+
+    // Move to another state, refusing the changes the lifecycle doesn't allow
+    pub fn transition(&mut self, to: ProcessState, at: u64) -> Result<(), TransitionError> {
+        if !self.state.can_transition_to(to) {
+            return Err(TransitionError { process_id: self.id, from: self.state, to });
+        }
+        self.state = to;
+        self.transitions.push((to, at));
+        Ok(())
+    }
+
+    // Last time the process entered a state
+    pub fn entered_at(&self, state: ProcessState) -> Option<u64> {
+        self.transitions.iter().rev().find(|(s, _)| *s == state).map(|(_, at)| *at)
+    }
+
+    // Size of the process state to copy when migrating, one page per MB of RAM
+    pub fn state_pages(&self) -> u64 {
+        self.needed_resources.ram
     }
 }
 
@@ -28,6 +179,23 @@ mod tests {
         assert_eq!(process.needed_resources, resources);
     }
 
+    #[test]
+    fn test_process_state_pages() {
+        let process = Process::new(4, "Pages Task".to_string(), Resources::new(2048, 100_000, 1));
+
+        assert_eq!(process.state_pages(), 2048);
+        assert!(!process.paused);
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let process = Process::new(5, "Normal Task".to_string(), Resources::new(1024, 100_000, 1));
+
+        assert_eq!(process.priority, Priority::Normal);
+        assert!(Priority::Critical > Priority::High);
+        assert!(Priority::Normal > Priority::BestEffort);
+    }
+
     #[test]
     fn test_process_clone() {
         let resources = Resources::new(8192, 500_000, 4);
@@ -52,4 +220,33 @@ mod tests {
         assert!(debug_str.contains("task: \"Debug Task\""));
         assert!(debug_str.contains("needed_resources: Resources"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        let mut process = Process::new(1, "Lifecycle".to_string(), Resources::new(1, 1, 1));
+        assert_eq!(process.state, ProcessState::Pending);
+
+        process.transition(ProcessState::Scheduled, 10).unwrap();
+        process.transition(ProcessState::Running, 20).unwrap();
+        // Preempted and started again
+        process.transition(ProcessState::Pending, 30).unwrap();
+        process.transition(ProcessState::Running, 40).unwrap();
+        process.transition(ProcessState::Succeeded, 50).unwrap();
+
+        assert_eq!(process.entered_at(ProcessState::Running), Some(40));
+        assert_eq!(process.transitions.len(), 5);
+        assert_eq!(
+            process.transition(ProcessState::Running, 60),
+            Err(TransitionError { process_id: 1, from: ProcessState::Succeeded, to: ProcessState::Running })
+        );
+        assert_eq!(process.state, ProcessState::Succeeded);
+    }
+
+    #[test]
+    fn test_invalid_transitions() {
+        assert!(!ProcessState::Pending.can_transition_to(ProcessState::Succeeded));
+        assert!(!ProcessState::Scheduled.can_transition_to(ProcessState::Succeeded));
+        assert!(!ProcessState::Cancelled.can_transition_to(ProcessState::Pending));
+        assert!(ProcessState::Failed.is_terminal());
+    }
+}
@@ -0,0 +1,224 @@
+use crate::affinity::is_allowed;
+use crate::node::Node;
+use crate::process::Process;
+use crate::resource::Resources;
+use crate::session::Session;
+use crate::scheduler::dominant_share;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RebalanceConfig {
+    // Time between two rounds, also the period of `spawn_rebalancer`
+    pub interval: u64,
+    // Difference of load between the busiest and the idlest node that is tolerated
+    pub threshold: f64,
+    // Most processes moved in one round
+    pub budget: usize,
+    // Time a moved process stays where it is before it can be moved again
+    pub cooldown: u64,
+}
+
+impl Default for RebalanceConfig {
+    fn default() -> Self {
+        RebalanceConfig {
+            interval: 10_000,
+            threshold: 0.25,
+            budget: 2,
+            cooldown: 60_000,
+        }
+    }
+}
+
+// Periodically moves processes away from the busiest nodes through `RedistributeProcess` actions
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rebalancer {
+    pub config: RebalanceConfig,
+    pub last_run: Option<u64>,
+    // Last time each process was proposed for a move
+    pub last_moved: HashMap<u32, u64>,
+}
+
+// Share of a node's capacity in use, over its most used resource
+pub fn node_load(total: &Resources, available: &Resources) -> f64 {
+    let used = Resources::new(
+        total.ram.saturating_sub(available.ram),
+        total.disk_space.saturating_sub(available.disk_space),
+        total.threads.saturating_sub(available.threads),
+    );
+    dominant_share(&used, total)
+}
+
+// Difference of load between the busiest and the idlest node
+pub fn imbalance(nodes: &[&Node]) -> f64 {
+    let loads = nodes.iter().map(|n| node_load(&n.total_resources, &n.available_resources));
+    let max = loads.clone().fold(f64::MIN, f64::max);
+    let min = loads.fold(f64::MAX, f64::min);
+    if nodes.is_empty() { 0.0 } else { max - min }
+}
+
+// A node during planning, moves are applied to it before being proposed
+struct PlannedNode<'a> {
+    node: &'a Node,
+    available: Resources,
+    processes: Vec<&'a Process>,
+}
+
+impl PlannedNode<'_> {
+    fn load(&self) -> f64 {
+        node_load(&self.node.total_resources, &self.available)
+    }
+
+    fn load_with(&self, process: &Process) -> f64 {
+        let mut available = self.available.clone();
+        available.allocate(&process.needed_resources);
+        node_load(&self.node.total_resources, &available)
+    }
+
+    fn load_without(&self, process: &Process) -> f64 {
+        let mut available = self.available.clone();
+        available.deallocate(&process.needed_resources);
+        node_load(&self.node.total_resources, &available)
+    }
+}
+
+impl Rebalancer {
+    pub fn new(config: RebalanceConfig) -> Self {
+        Rebalancer { config, last_run: None, last_moved: HashMap::new() }
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        match self.last_run {
+            Some(last_run) => now >= last_run + self.config.interval,
+            None => true,
+        }
+    }
+
+    pub fn in_cooldown(&self, process_id: u32, now: u64) -> bool {
+        self.last_moved.get(&process_id).is_some_and(|&moved_at| now < moved_at + self.config.cooldown)
+    }
+
+    // Processes to move with their target node, at most `budget` of them. Each one goes from the busiest node to the
+    // idlest one, choosing the process that lowers the busiest load the most, while the imbalance is over the threshold.
+    pub fn plan(&self, nodes: &[&Node], now: u64) -> Vec<(u32, u32)> {
+        let mut planned: Vec<PlannedNode> = nodes
+            .iter()
+            .map(|node| PlannedNode {
+                node,
+                available: node.available_resources.clone(),
+                processes: node.active_processes.iter().collect(),
+            })
+            .collect();
+
+        let mut moves = Vec::new();
+        while moves.len() < self.config.budget && planned.len() > 1 {
+            let by_load = |a: &&PlannedNode, b: &&PlannedNode| a.load().total_cmp(&b.load());
+            let hot = planned.iter().enumerate().max_by(|a, b| by_load(&a.1, &b.1)).map(|(i, _)| i).unwrap();
+            let cold = planned.iter().enumerate().min_by(|a, b| by_load(&a.1, &b.1)).map(|(i, _)| i).unwrap();
+            let hot_load = planned[hot].load();
+            if hot_load - planned[cold].load() <= self.config.threshold {
+                break;
+            }
+
+            let cold_ids: Vec<u32> = planned[cold].processes.iter().map(|p| p.id).collect();
+            let candidate = planned[hot]
+                .processes
+                .iter()
+                .filter(|p| !p.paused && !self.in_cooldown(p.id, now))
+                .filter(|p| planned[cold].available.can_allocate(&p.needed_resources))
+                .filter(|p| is_allowed(&p.constraints, &planned[cold].node.labels, &cold_ids))
+                .map(|p| (p, planned[hot].load_without(p).max(planned[cold].load_with(p))))
+                .filter(|(_, peak)| *peak < hot_load)
+                .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.id.cmp(&b.0.id)))
+                .map(|(p, _)| *p);
+            let process = match candidate {
+                Some(process) => process,
+                None => break,
+            };
+
+            planned[hot].processes.retain(|p| p.id != process.id);
+            planned[hot].available.deallocate(&process.needed_resources);
+            planned[cold].processes.push(process);
+            planned[cold].available.allocate(&process.needed_resources);
+            moves.push((process.id, planned[cold].node.id));
+        }
+        moves
+    }
+}
+
+// Run `Session::rebalance` every `interval` of the session's rebalancer, moving a simulated clock forward
+// by the same amount. Runs until the task is aborted or the session has no rebalancer.
+pub fn spawn_rebalancer(session: Arc<Mutex<Session>>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let interval = match &session.lock().await.rebalancer {
+                Some(rebalancer) => rebalancer.config.interval,
+                None => return,
+            };
+            sleep(Duration::from_millis(interval)).await;
+            let mut session = session.lock().await;
+            session.clock.advance(interval);
+            session.rebalance();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u32, processes: &[(u32, u64)]) -> Node {
+        let mut node = Node::with_resources(id, Resources::new(8_192, 100_000, 8));
+        for &(process_id, ram) in processes {
+            let process = Process::new(process_id, format!("Process {}", process_id), Resources::new(ram, 1_000, 1));
+            node.allocate(&process.needed_resources);
+            node.active_processes.push(process);
+        }
+        node
+    }
+
+    #[test]
+    fn test_node_load_and_imbalance() {
+        let busy = node(1, &[(1, 4_096), (2, 2_048)]);
+        let idle = node(2, &[]);
+
+        assert_eq!(node_load(&busy.total_resources, &busy.available_resources), 0.75);
+        assert_eq!(imbalance(&[&busy, &idle]), 0.75);
+        assert_eq!(imbalance(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_plan_moves_from_busiest_to_idlest() {
+        let busy = node(1, &[(1, 4_096), (2, 2_048), (3, 1_024)]);
+        let idle = node(2, &[]);
+        let rebalancer = Rebalancer::new(RebalanceConfig::default());
+
+        // Moving process 1 leaves loads of 0.375 and 0.5, better than any other single move
+        assert_eq!(rebalancer.plan(&[&busy, &idle], 0), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_plan_respects_budget_and_cooldown() {
+        let busy = node(1, &[(1, 2_048), (2, 2_048), (3, 2_048), (4, 2_048)]);
+        let idle = node(2, &[]);
+        let mut rebalancer = Rebalancer::new(RebalanceConfig { budget: 1, threshold: 0.0, ..RebalanceConfig::default() });
+        assert_eq!(rebalancer.plan(&[&busy, &idle], 0), vec![(1, 2)]);
+
+        rebalancer.last_moved.insert(1, 0);
+        assert_eq!(rebalancer.plan(&[&busy, &idle], 1_000), vec![(2, 2)]);
+        assert_eq!(rebalancer.plan(&[&busy, &idle], 60_000), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_balanced_cluster_is_left_alone() {
+        let a = node(1, &[(1, 4_096)]);
+        let b = node(2, &[(2, 2_048)]);
+        let rebalancer = Rebalancer::new(RebalanceConfig::default());
+
+        assert!(rebalancer.plan(&[&a, &b], 0).is_empty());
+        assert!(rebalancer.is_due(0));
+    }
+}
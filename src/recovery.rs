@@ -0,0 +1,120 @@
+use crate::node::{Node, NodeStatus};
+use crate::resource::Resources;
+
+// Decides whether a recovering node is fit to rejoin as Active
+pub type HealthCheck = fn(&Node) -> bool;
+
+// Default health check: the node is recovering, has caught up with the cluster
+// and has sent a heartbeat since it was reinstalled
+pub fn default_health_check(node: &Node) -> bool {
+    node.status == NodeStatus::Recovering && node.caught_up && node.heartbeat_since_reinstall
+}
+
+#[derive(Clone, Debug)]
+pub struct RecoveryPolicy {
+    // Number of reinstall attempts before giving up on the node
+    pub max_attempts: u32,
+    // Delay (ms) before the first attempt, and between the first and the second
+    pub initial_backoff: u64,
+    // Upper bound (ms) for the delay between attempts
+    pub max_backoff: u64,
+    // Growth factor of the delay after each failed attempt
+    pub multiplier: u64,
+    pub health_check: HealthCheck,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        RecoveryPolicy {
+            max_attempts: 3,
+            initial_backoff: 1_000,
+            max_backoff: 30_000,
+            multiplier: 2,
+            health_check: default_health_check,
+        }
+    }
+}
+
+impl RecoveryPolicy {
+    // Delay to wait after the given (1-based) failed attempt
+    pub fn backoff(&self, attempt: u32) -> u64 {
+        let mut delay = self.initial_backoff;
+        for _ in 1..attempt {
+            delay = delay.saturating_mul(self.multiplier);
+            if delay >= self.max_backoff {
+                return self.max_backoff;
+            }
+        }
+        delay.min(self.max_backoff)
+    }
+}
+
+// Progress of a node going through the recovery workflow
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveryState {
+    pub node_id: u32,
+    pub attempts: u32,
+    pub next_attempt_at: u64,
+    // Processes the node was running when it failed
+    pub former_assignments: Vec<u32>,
+    // Capacity the node is reinstalled with
+    pub capacity: Resources,
+}
+
+impl RecoveryState {
+    pub fn new(node_id: u32, former_assignments: Vec<u32>, capacity: Resources, first_attempt_at: u64) -> Self {
+        RecoveryState {
+            node_id,
+            attempts: 0,
+            next_attempt_at: first_attempt_at,
+            former_assignments,
+            capacity,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecoveryOutcome {
+    // The node passed its health checks and is Active again
+    Recovered,
+    // The attempt failed, a new one is scheduled
+    Retrying { next_attempt_at: u64 },
+    // Retry limit reached, the node was removed
+    GaveUp,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RecoveryPolicy {
+            initial_backoff: 1_000,
+            max_backoff: 5_000,
+            multiplier: 2,
+            ..RecoveryPolicy::default()
+        };
+
+        assert_eq!(policy.backoff(1), 1_000);
+        assert_eq!(policy.backoff(2), 2_000);
+        assert_eq!(policy.backoff(3), 4_000);
+        assert_eq!(policy.backoff(4), 5_000);
+        assert_eq!(policy.backoff(50), 5_000);
+    }
+
+    #[test]
+    fn test_default_health_check() {
+        let mut node = Node::new(1);
+        assert!(!default_health_check(&node));
+
+        node.status = NodeStatus::Recovering;
+        assert!(!default_health_check(&node));
+
+        node.caught_up = true;
+        assert!(!default_health_check(&node));
+
+        node.heartbeat_since_reinstall = true;
+        assert!(default_health_check(&node));
+    }
+}
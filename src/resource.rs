@@ -1,4 +1,6 @@
 use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Resources {
@@ -39,6 +41,31 @@ impl Resources {
         self.threads += released.threads;
     }
 
+    // No resource at all, e.g. a node created without capacity
+    pub fn is_zero(&self) -> bool {
+        self.ram == 0 && self.disk_space == 0 && self.threads == 0
+    }
+
+}
+
+impl Add for &Resources {
+    type Output = Resources;
+
+    fn add(self, other: &Resources) -> Resources {
+        Resources::new(self.ram + other.ram, self.disk_space + other.disk_space, self.threads + other.threads)
+    }
+}
+
+impl AddAssign<&Resources> for Resources {
+    fn add_assign(&mut self, other: &Resources) {
+        *self = &*self + other;
+    }
+}
+
+impl<'a> Sum<&'a Resources> for Resources {
+    fn sum<I: Iterator<Item = &'a Resources>>(iter: I) -> Resources {
+        iter.fold(Resources::new(0, 0, 0), |total, resources| &total + resources)
+    }
 }
 
 impl fmt::Display for Resources {
@@ -143,6 +170,19 @@ mod tests {
         assert_eq!(available.threads, 6);           // 4 + 2
     }
 
+    #[test]
+    fn test_add_and_sum() {
+        let a = Resources::new(8000, 500_000, 4);
+        let b = Resources::new(4000, 200_000, 2);
+
+        assert_eq!(&a + &b, Resources::new(12_000, 700_000, 6));
+        let mut c = a.clone();
+        c += &b;
+        assert_eq!(c, Resources::new(12_000, 700_000, 6));
+        assert_eq!([a, b].iter().sum::<Resources>(), Resources::new(12_000, 700_000, 6));
+        assert!(Vec::<Resources>::new().iter().sum::<Resources>().is_zero());
+    }
+
     #[test]
     fn test_display() {
         let resources = Resources::new(8192, 500_000, 4);
@@ -0,0 +1,221 @@
+use crate::process::{CommandSpec, ProcessOutput, ProcessState};
+use crate::session::Session;
+use std::io;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+// A command being run for a process placed on a node. Dropping it kills the OS child.
+#[derive(Debug)]
+pub struct RunningCommand {
+    pub node_id: u32,
+    // Start of the run of the process the command belongs to, a requeued process starts a new run
+    pub started_at: Option<u64>,
+    pub kill: oneshot::Sender<()>,
+}
+
+// Run a command to completion, capturing its exit status, stdout and stderr
+pub async fn execute(command: &CommandSpec) -> io::Result<ProcessOutput> {
+    let mut child = Command::new(&command.program);
+    child
+        .args(&command.args)
+        .envs(&command.env)
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    if let Some(working_dir) = &command.working_dir {
+        child.current_dir(working_dir);
+    }
+
+    let output = child.output().await?;
+    Ok(ProcessOutput {
+        exit_code: output.status.code(),
+        success: output.status.success(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    })
+}
+
+// Run the command of a process placed on a node, then complete or fail it, which releases its resources.
+// The session is only locked before and after the command runs. The command is registered in
+// `Session::running_commands`: cancelling, failing or requeueing the process kills it, and its exit is then ignored.
+// None when the process isn't running or has no command.
+pub async fn run_process(session: Arc<Mutex<Session>>, process_id: u32) -> Option<io::Result<ProcessOutput>> {
+    let (node_id, started_at, command, killed) = {
+        let mut session = session.lock().await;
+        let (node_id, started_at, command) = session.nodes.iter().find_map(|node| {
+            let process = node
+                .active_processes
+                .iter()
+                .find(|p| p.id == process_id && p.state == ProcessState::Running)?;
+            Some((node.id, process.started_at, process.command.clone()?))
+        })?;
+        let (kill, killed) = oneshot::channel();
+        session.running_commands.insert(process_id, RunningCommand { node_id, started_at, kill });
+        (node_id, started_at, command, killed)
+    };
+
+    println!("Node {} running process {}: {} {:?}", node_id, process_id, command.program, command.args);
+    let (result, killed) = tokio::select! {
+        result = execute(&command) => (result, false),
+        // The child is killed when the `execute` future is dropped
+        _ = killed => (Err(io::Error::new(io::ErrorKind::Interrupted, "command killed")), true),
+    };
+
+    // The process may have moved to another node meanwhile, but not started a new run
+    let mut session = session.lock().await;
+    let current = !killed
+        && session
+            .running_commands
+            .get(&process_id)
+            .is_some_and(|running| running.started_at == started_at);
+    if current {
+        session.running_commands.remove(&process_id);
+        session.record_exit(process_id, &result);
+    } else {
+        println!("Command of process {} killed, its exit is ignored", process_id);
+    }
+    Some(result)
+}
+
+// `run_process` in its own task
+pub fn spawn_process(session: Arc<Mutex<Session>>, process_id: u32) -> JoinHandle<Option<io::Result<ProcessOutput>>> {
+    tokio::spawn(run_process(session, process_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::Node;
+    use crate::process::Process;
+    use crate::resource::Resources;
+
+    #[cfg(unix)]
+    fn shell(script: &str) -> CommandSpec {
+        CommandSpec::new("sh", &["-c", script])
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_captures_output() {
+        let output = execute(&shell("echo hello; echo oops >&2")).await.unwrap();
+
+        assert!(output.success);
+        assert_eq!(output.exit_code, Some(0));
+        assert_eq!(output.stdout, "hello\n");
+        assert_eq!(output.stderr, "oops\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_exit_status() {
+        let output = execute(&shell("exit 3")).await.unwrap();
+
+        assert!(!output.success);
+        assert_eq!(output.exit_code, Some(3));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_env_and_working_dir() {
+        let mut command = shell("echo $GREETING; pwd");
+        command.env.insert("GREETING".to_string(), "hola".to_string());
+        command.working_dir = Some(std::env::temp_dir());
+
+        let output = execute(&command).await.unwrap();
+        let expected_dir = std::env::temp_dir().canonicalize().unwrap();
+        assert_eq!(output.stdout, format!("hola\n{}\n", expected_dir.display()));
+    }
+
+    async fn session_running(id: u32, command: CommandSpec) -> Arc<Mutex<Session>> {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(4_096, 10_000, 2));
+        let mut process = Process::new(id, "Job".to_string(), Resources::new(2_048, 1_000, 1));
+        process.command = Some(command);
+        session.processes.push(process);
+        session.assign_processes().await;
+        Arc::new(Mutex::new(session))
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_process_releases_resources() {
+        let session = session_running(1, shell("echo done")).await;
+
+        let output = spawn_process(Arc::clone(&session), 1).await.unwrap().unwrap().unwrap();
+        assert_eq!(output.stdout, "done\n");
+
+        let session = session.lock().await;
+        assert_eq!(session.process_state(1), Some(ProcessState::Succeeded));
+        assert_eq!(session.finished_processes[0].output, Some(output));
+        assert_eq!(session.available_resources, Resources::new(4_096, 10_000, 2));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_command_fails_process() {
+        let session = session_running(1, shell("echo broken >&2; exit 2")).await;
+        run_process(Arc::clone(&session), 1).await.unwrap().unwrap();
+        {
+            let session = session.lock().await;
+            assert_eq!(session.process_state(1), Some(ProcessState::Failed));
+            assert_eq!(session.finished_processes[0].output.as_ref().unwrap().exit_code, Some(2));
+        }
+
+        // The program doesn't exist
+        let session = session_running(2, CommandSpec::new("/nonexistent/program", &[])).await;
+        assert!(run_process(Arc::clone(&session), 2).await.unwrap().is_err());
+        assert_eq!(session.lock().await.process_state(2), Some(ProcessState::Failed));
+        // Nothing to run: unknown process
+        assert!(run_process(session, 3).await.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_kills_command() {
+        let session = session_running(1, shell("sleep 5")).await;
+        let handle = spawn_process(Arc::clone(&session), 1);
+        while !session.lock().await.running_commands.contains_key(&1) {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(session.lock().await.cancel_process(1));
+        let result = tokio::time::timeout(std::time::Duration::from_secs(2), handle).await.unwrap().unwrap();
+        assert_eq!(result.unwrap().unwrap_err().kind(), io::ErrorKind::Interrupted);
+        let session = session.lock().await;
+        assert_eq!(session.process_state(1), Some(ProcessState::Cancelled));
+        assert!(session.running_commands.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exit_of_killed_command_ignored_after_requeue() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(8_192, 20_000, 4));
+        let mut process = Process::new(1, "Job".to_string(), Resources::new(2_048, 1_000, 1));
+        process.command = Some(shell("sleep 5"));
+        session.processes.push(process);
+        session.assign_processes().await;
+        let session = Arc::new(Mutex::new(session));
+        let handle = spawn_process(Arc::clone(&session), 1);
+        while !session.lock().await.running_commands.contains_key(&1) {
+            tokio::task::yield_now().await;
+        }
+
+        // The node fails, the process starts over on the other node
+        let node_id = session.lock().await.running_commands[&1].node_id;
+        session.lock().await.handle_node_failure(node_id, "Crash".to_string());
+        let result = tokio::time::timeout(std::time::Duration::from_secs(2), handle).await.unwrap().unwrap();
+        assert!(result.unwrap().is_err());
+
+        let session = session.lock().await;
+        assert_eq!(session.process_state(1), Some(ProcessState::Running));
+        assert!(session.finished_processes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_unknown_program() {
+        let command = CommandSpec::new("/nonexistent/program", &[]);
+
+        assert!(execute(&command).await.is_err());
+    }
+}
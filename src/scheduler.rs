@@ -0,0 +1,561 @@
+use crate::affinity::{is_allowed, preference_score};
+use crate::process::Process;
+use crate::tenant::{tenant_of, within_quota};
+use crate::resource::Resources;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+// A schedulable node as seen by the scheduler
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeSnapshot {
+    pub id: u32,
+    pub total_resources: Resources,
+    pub available_resources: Resources,
+    pub labels: HashMap<String, String>,
+    // Processes running on the node
+    pub process_ids: Vec<u32>,
+}
+
+impl NodeSnapshot {
+    pub fn can_host(&self, process: &Process) -> bool {
+        self.available_resources.can_allocate(&process.needed_resources)
+    }
+
+    // Whether the process' placement constraints allow this node
+    pub fn is_allowed(&self, process: &Process) -> bool {
+        is_allowed(&process.constraints, &self.labels, &self.process_ids)
+    }
+
+    // Fraction of the node's capacity left free after placing the process, summed over ram, disk and threads
+    pub fn remaining_share(&self, process: &Process) -> f64 {
+        let needed = &process.needed_resources;
+        let share = |available: u64, needed: u64, total: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                available.saturating_sub(needed) as f64 / total as f64
+            }
+        };
+        share(self.available_resources.ram, needed.ram, self.total_resources.ram)
+            + share(self.available_resources.disk_space, needed.disk_space, self.total_resources.disk_space)
+            + share(
+                self.available_resources.threads as u64,
+                needed.threads as u64,
+                self.total_resources.threads as u64,
+            )
+    }
+}
+
+// The nodes that can receive processes, sorted by id
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterSnapshot {
+    pub nodes: Vec<NodeSnapshot>,
+    // Resources used by each tenant across the cluster, and their quotas
+    pub tenant_usage: HashMap<String, Resources>,
+    pub quotas: HashMap<String, Resources>,
+}
+
+impl ClusterSnapshot {
+    // Nodes allowed by the process' constraints that have room for it, none if its tenant is out of quota.
+    // If some of them satisfy its preferred affinities, only the best matching ones are returned.
+    pub fn candidates<'a>(&'a self, process: &'a Process) -> impl Iterator<Item = &'a NodeSnapshot> {
+        let within_quota = self.within_quota(process);
+        let allowed: Vec<&NodeSnapshot> = self
+            .nodes
+            .iter()
+            .filter(|node| within_quota && node.can_host(process) && node.is_allowed(process))
+            .collect();
+        let best = allowed
+            .iter()
+            .map(|node| preference_score(&process.constraints, &node.labels))
+            .max()
+            .unwrap_or(0);
+        allowed
+            .into_iter()
+            .filter(move |node| preference_score(&process.constraints, &node.labels) == best)
+    }
+
+    // Account for a process placed on a node, so later decisions of a batch see it
+    pub fn reserve(&mut self, node_id: u32, process: &Process) {
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.id == node_id) {
+            node.available_resources.allocate(&process.needed_resources);
+            node.process_ids.push(process.id);
+        }
+        *self.tenant_usage.entry(tenant_of(process).to_string()).or_insert_with(|| Resources::new(0, 0, 0)) +=
+            &process.needed_resources;
+    }
+
+    pub fn within_quota(&self, process: &Process) -> bool {
+        within_quota(&self.quotas, &self.tenant_usage, process)
+    }
+
+    // Capacity of all the nodes together
+    pub fn total_resources(&self) -> Resources {
+        self.nodes.iter().map(|n| &n.total_resources).sum()
+    }
+
+    // Reason for not placing a process when no candidate was found
+    pub fn rejection(&self, process: &Process) -> Rejection {
+        if self.nodes.is_empty() {
+            Rejection::NoNodes
+        } else if !self.within_quota(process) {
+            Rejection::QuotaExceeded { process_id: process.id, tenant: tenant_of(process).to_string() }
+        } else if !self.nodes.iter().any(|node| node.is_allowed(process)) {
+            Rejection::ConstraintsUnsatisfied { process_id: process.id }
+        } else {
+            Rejection::InsufficientResources { process_id: process.id }
+        }
+    }
+}
+
+// Why a process could not be placed
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rejection {
+    // No node can receive processes right now
+    NoNodes,
+    // No node has room for the process
+    InsufficientResources { process_id: u32 },
+    // No node satisfies the process' placement constraints
+    ConstraintsUnsatisfied { process_id: u32 },
+    // The process' tenant has no quota left for it
+    QuotaExceeded { process_id: u32, tenant: String },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::NoNodes => write!(f, "No nodes available"),
+            Rejection::InsufficientResources { process_id } => {
+                write!(f, "No node has enough resources for process {}", process_id)
+            }
+            Rejection::ConstraintsUnsatisfied { process_id } => {
+                write!(f, "No node satisfies the placement constraints of process {}", process_id)
+            }
+            Rejection::QuotaExceeded { process_id, tenant } => {
+                write!(f, "Process {} exceeds the quota of tenant '{}'", process_id, tenant)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+// Chooses the node a pending process is placed on
+pub trait Scheduler: Send {
+    fn name(&self) -> &str;
+
+    // Id of the node to place the process on
+    fn place(&mut self, process: &Process, cluster: &ClusterSnapshot) -> Result<u32, Rejection>;
+
+    // Place several pending processes, one result per process in the same order.
+    // By default they are placed one after the other in the given order.
+    fn place_batch(&mut self, processes: &[Process], cluster: &mut ClusterSnapshot) -> Vec<Result<u32, Rejection>> {
+        processes
+            .iter()
+            .map(|process| {
+                let placement = self.place(process, cluster);
+                if let Ok(node_id) = placement {
+                    cluster.reserve(node_id, process);
+                }
+                placement
+            })
+            .collect()
+    }
+}
+
+// Fraction of a capacity taken by an amount, 0 when there is no capacity
+fn fraction(amount: u64, capacity: u64) -> f64 {
+    if capacity == 0 {
+        0.0
+    } else {
+        amount as f64 / capacity as f64
+    }
+}
+
+// Largest fraction of the capacity some resources (a process' needs, a tenant's usage...) take over ram, disk and threads
+pub fn dominant_share(resources: &Resources, capacity: &Resources) -> f64 {
+    fraction(resources.ram, capacity.ram)
+        .max(fraction(resources.disk_space, capacity.disk_space))
+        .max(fraction(resources.threads as u64, capacity.threads as u64))
+}
+
+// Node with the fewest active processes
+#[derive(Clone, Debug, Default)]
+pub struct LeastLoaded;
+
+impl Scheduler for LeastLoaded {
+    fn name(&self) -> &str {
+        "least-loaded"
+    }
+
+    fn place(&mut self, process: &Process, cluster: &ClusterSnapshot) -> Result<u32, Rejection> {
+        cluster
+            .candidates(process)
+            .min_by_key(|node| node.process_ids.len())
+            .map(|node| node.id)
+            .ok_or_else(|| cluster.rejection(process))
+    }
+}
+
+// Takes turns over the nodes, skipping the ones without room
+#[derive(Clone, Debug, Default)]
+pub struct RoundRobin {
+    pub last_node_id: Option<u32>,
+}
+
+impl Scheduler for RoundRobin {
+    fn name(&self) -> &str {
+        "round-robin"
+    }
+
+    fn place(&mut self, process: &Process, cluster: &ClusterSnapshot) -> Result<u32, Rejection> {
+        let last = self.last_node_id;
+        let next = cluster
+            .candidates(process)
+            // None is lower than any id, the first round starts from the lowest node
+            .find(|node| Some(node.id) > last)
+            .or_else(|| cluster.candidates(process).next())
+            .map(|node| node.id);
+        self.last_node_id = next.or(self.last_node_id);
+        next.ok_or_else(|| cluster.rejection(process))
+    }
+}
+
+// Any node with room, chosen by a seeded generator so runs are reproducible
+#[derive(Clone, Debug)]
+pub struct Random {
+    rng: SmallRng,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { rng: SmallRng::seed_from_u64(seed) }
+    }
+}
+
+impl Scheduler for Random {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn place(&mut self, process: &Process, cluster: &ClusterSnapshot) -> Result<u32, Rejection> {
+        let candidates: Vec<u32> = cluster.candidates(process).map(|node| node.id).collect();
+        if candidates.is_empty() {
+            return Err(cluster.rejection(process));
+        }
+        Ok(candidates[self.rng.gen_range(0..candidates.len())])
+    }
+}
+
+// Node left with the least free capacity, keeps big nodes free for big processes
+#[derive(Clone, Debug, Default)]
+pub struct BestFit;
+
+impl Scheduler for BestFit {
+    fn name(&self) -> &str {
+        "best-fit"
+    }
+
+    fn place(&mut self, process: &Process, cluster: &ClusterSnapshot) -> Result<u32, Rejection> {
+        cluster
+            .candidates(process)
+            .min_by(|a, b| a.remaining_share(process).total_cmp(&b.remaining_share(process)))
+            .map(|node| node.id)
+            .ok_or_else(|| cluster.rejection(process))
+    }
+}
+
+// Node left with the most free capacity, spreads the load
+#[derive(Clone, Debug, Default)]
+pub struct WorstFit;
+
+impl Scheduler for WorstFit {
+    fn name(&self) -> &str {
+        "worst-fit"
+    }
+
+    fn place(&mut self, process: &Process, cluster: &ClusterSnapshot) -> Result<u32, Rejection> {
+        cluster
+            .candidates(process)
+            .max_by(|a, b| a.remaining_share(process).total_cmp(&b.remaining_share(process)))
+            .map(|node| node.id)
+            .ok_or_else(|| cluster.rejection(process))
+    }
+}
+
+// Vector bin-packing over ram, disk and threads. Each process goes to the node whose free
+// resources, once it is placed, are smallest as a normalized vector (norm-based greedy),
+// which fills nodes up evenly in every dimension and leaves whole nodes free for big processes.
+// In batch mode the pending processes are placed together, by priority and then biggest
+// dominant share first (first fit decreasing), instead of in insertion order.
+#[derive(Clone, Debug, Default)]
+pub struct BinPacking {
+    pub batch: bool,
+}
+
+impl BinPacking {
+    // Squared norm of the node's free resources after placing the process, relative to its capacity
+    fn leftover(node: &NodeSnapshot, process: &Process) -> f64 {
+        let available = &node.available_resources;
+        let needed = &process.needed_resources;
+        let total = &node.total_resources;
+        [
+            fraction(available.ram.saturating_sub(needed.ram), total.ram),
+            fraction(available.disk_space.saturating_sub(needed.disk_space), total.disk_space),
+            fraction(available.threads.saturating_sub(needed.threads) as u64, total.threads as u64),
+        ]
+        .iter()
+        .map(|share| share * share)
+        .sum()
+    }
+}
+
+impl Scheduler for BinPacking {
+    fn name(&self) -> &str {
+        "bin-packing"
+    }
+
+    fn place(&mut self, process: &Process, cluster: &ClusterSnapshot) -> Result<u32, Rejection> {
+        cluster
+            .candidates(process)
+            .min_by(|a, b| Self::leftover(a, process).total_cmp(&Self::leftover(b, process)))
+            .map(|node| node.id)
+            .ok_or_else(|| cluster.rejection(process))
+    }
+
+    fn place_batch(&mut self, processes: &[Process], cluster: &mut ClusterSnapshot) -> Vec<Result<u32, Rejection>> {
+        let mut order: Vec<usize> = (0..processes.len()).collect();
+        if self.batch {
+            let capacity = cluster.total_resources();
+            order.sort_by(|&a, &b| {
+                processes[b].priority.cmp(&processes[a].priority).then(
+                    dominant_share(&processes[b].needed_resources, &capacity)
+                        .total_cmp(&dominant_share(&processes[a].needed_resources, &capacity)),
+                )
+            });
+        }
+
+        let mut placements = vec![Err(Rejection::NoNodes); processes.len()];
+        for index in order {
+            let placement = self.place(&processes[index], cluster);
+            if let Ok(node_id) = placement {
+                cluster.reserve(node_id, &processes[index]);
+            }
+            placements[index] = placement;
+        }
+        placements
+    }
+}
+
+// Dominant resource fairness: the pending processes are placed one at a time, always for the tenant
+// with pending work whose dominant share (its largest fraction of the cluster's ram, disk or threads)
+// is the lowest, so capacity is divided fairly among the tenants. Each process goes to the least loaded node.
+#[derive(Clone, Debug, Default)]
+pub struct DominantResourceFairness;
+
+impl Scheduler for DominantResourceFairness {
+    fn name(&self) -> &str {
+        "drf"
+    }
+
+    fn place(&mut self, process: &Process, cluster: &ClusterSnapshot) -> Result<u32, Rejection> {
+        LeastLoaded.place(process, cluster)
+    }
+
+    fn place_batch(&mut self, processes: &[Process], cluster: &mut ClusterSnapshot) -> Vec<Result<u32, Rejection>> {
+        let capacity = cluster.total_resources();
+        // Pending processes of every tenant, keeping the queue order
+        let mut queues: Vec<(String, VecDeque<usize>)> = Vec::new();
+        for (index, process) in processes.iter().enumerate() {
+            let tenant = tenant_of(process);
+            match queues.iter_mut().find(|(t, _)| t == tenant) {
+                Some((_, queue)) => queue.push_back(index),
+                None => queues.push((tenant.to_string(), VecDeque::from([index]))),
+            }
+        }
+
+        let mut placements = vec![Err(Rejection::NoNodes); processes.len()];
+        loop {
+            let share = |tenant: &str| {
+                cluster
+                    .tenant_usage
+                    .get(tenant)
+                    .map_or(0.0, |usage| dominant_share(usage, &capacity))
+            };
+            let next = queues
+                .iter_mut()
+                .filter(|(_, queue)| !queue.is_empty())
+                .min_by(|(a, _), (b, _)| share(a).total_cmp(&share(b)))
+                .and_then(|(_, queue)| queue.pop_front());
+            let index = match next {
+                Some(index) => index,
+                None => break,
+            };
+
+            let placement = self.place(&processes[index], cluster);
+            if let Ok(node_id) = placement {
+                cluster.reserve(node_id, &processes[index]);
+            }
+            placements[index] = placement;
+        }
+        placements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: u32, available: Resources, active_processes: u32) -> NodeSnapshot {
+        NodeSnapshot {
+            id,
+            total_resources: Resources::new(8_192, 100_000, 8),
+            available_resources: available,
+            labels: HashMap::new(),
+            process_ids: (100..100 + active_processes).collect(),
+        }
+    }
+
+    fn cluster() -> ClusterSnapshot {
+        ClusterSnapshot {
+            nodes: vec![
+                node(1, Resources::new(8_192, 100_000, 8), 3),
+                node(2, Resources::new(2_048, 50_000, 2), 1),
+                node(3, Resources::new(1_024, 10_000, 1), 0),
+            ],
+            ..ClusterSnapshot::default()
+        }
+    }
+
+    fn process(ram: u64) -> Process {
+        Process::new(1, "Process 1".to_string(), Resources::new(ram, 1_000, 1))
+    }
+
+    #[test]
+    fn test_least_loaded() {
+        assert_eq!(LeastLoaded.place(&process(512), &cluster()), Ok(3));
+        // Node 3 is the least loaded but has no room
+        assert_eq!(LeastLoaded.place(&process(2_048), &cluster()), Ok(2));
+    }
+
+    #[test]
+    fn test_round_robin_skips_full_nodes() {
+        let mut scheduler = RoundRobin::default();
+
+        assert_eq!(scheduler.place(&process(512), &cluster()), Ok(1));
+        assert_eq!(scheduler.place(&process(512), &cluster()), Ok(2));
+        assert_eq!(scheduler.place(&process(2_048), &cluster()), Ok(1));
+    }
+
+    #[test]
+    fn test_random_is_reproducible() {
+        let run = |seed: u64| {
+            let mut scheduler = Random::new(seed);
+            (0..20).map(|_| scheduler.place(&process(512), &cluster()).unwrap()).collect::<Vec<u32>>()
+        };
+
+        assert_eq!(run(3), run(3));
+        assert!(run(3).iter().all(|id| (1..=3).contains(id)));
+    }
+
+    #[test]
+    fn test_best_and_worst_fit() {
+        assert_eq!(BestFit.place(&process(512), &cluster()), Ok(3));
+        assert_eq!(WorstFit.place(&process(512), &cluster()), Ok(1));
+    }
+
+    #[test]
+    fn test_rejection_reasons() {
+        assert_eq!(LeastLoaded.place(&process(512), &ClusterSnapshot::default()), Err(Rejection::NoNodes));
+        assert_eq!(
+            BestFit.place(&process(16_384), &cluster()),
+            Err(Rejection::InsufficientResources { process_id: 1 })
+        );
+    }
+
+    #[test]
+    fn test_bin_packing_fills_nodes_evenly() {
+        // Node 1 is left with spare threads only, node 2 with a bit of everything
+        let cluster = ClusterSnapshot {
+            nodes: vec![
+                node(1, Resources::new(1_024, 10_000, 6), 0),
+                node(2, Resources::new(2_048, 20_000, 2), 0),
+            ],
+            ..ClusterSnapshot::default()
+        };
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(1_024, 10_000, 2));
+
+        assert_eq!(BinPacking::default().place(&process, &cluster), Ok(2));
+    }
+
+    #[test]
+    fn test_batch_places_big_processes_first() {
+        let cluster = ClusterSnapshot {
+            nodes: vec![
+                node(1, Resources::new(8_192, 100_000, 8), 0),
+                node(2, Resources::new(4_096, 100_000, 8), 0),
+            ],
+            ..ClusterSnapshot::default()
+        };
+        let processes = vec![
+            Process::new(1, "Small".to_string(), Resources::new(4_096, 1_000, 1)),
+            Process::new(2, "Small".to_string(), Resources::new(4_096, 1_000, 1)),
+            Process::new(3, "Big".to_string(), Resources::new(8_192, 1_000, 1)),
+        ];
+
+        // In insertion order the small processes take node 1 and the big one doesn't fit
+        let placements = BinPacking::default().place_batch(&processes, &mut cluster.clone());
+        assert_eq!(placements[2], Err(Rejection::InsufficientResources { process_id: 3 }));
+
+        let placements = BinPacking { batch: true }.place_batch(&processes, &mut cluster.clone());
+        assert_eq!(placements[2], Ok(1));
+        assert!(placements[..2].contains(&Ok(2)));
+        assert!(placements[..2].iter().any(|p| p.is_err()));
+    }
+
+    #[test]
+    fn test_dominant_share() {
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(1_024, 50_000, 1));
+
+        assert_eq!(dominant_share(&process.needed_resources, &Resources::new(8_192, 100_000, 8)), 0.5);
+        assert_eq!(dominant_share(&Resources::new(0, 0, 0), &Resources::new(8_192, 100_000, 8)), 0.0);
+    }
+
+    fn owned(id: u32, tenant: &str) -> Process {
+        let mut process = Process::new(id, format!("Process {}", id), Resources::new(2_048, 1_000, 1));
+        process.tenant = Some(tenant.to_string());
+        process
+    }
+
+    #[test]
+    fn test_drf_shares_capacity_among_tenants() {
+        let cluster = ClusterSnapshot {
+            nodes: vec![node(1, Resources::new(8_192, 100_000, 8), 0)],
+            ..ClusterSnapshot::default()
+        };
+        // Tenant a queued first and asks for the whole node
+        let processes: Vec<Process> = (1..=4).map(|id| owned(id, "a")).chain((5..=6).map(|id| owned(id, "b"))).collect();
+
+        let placements = LeastLoaded.place_batch(&processes, &mut cluster.clone());
+        assert!(placements[..4].iter().all(|p| p.is_ok()));
+
+        let placements = DominantResourceFairness.place_batch(&processes, &mut cluster.clone());
+        let placed: Vec<u32> = processes.iter().zip(placements).filter(|(_, p)| p.is_ok()).map(|(p, _)| p.id).collect();
+        assert_eq!(placed, vec![1, 2, 5, 6]);
+    }
+
+    #[test]
+    fn test_quota_rejection() {
+        let mut cluster = cluster();
+        cluster.quotas.insert("a".to_string(), Resources::new(2_048, 10_000, 2));
+        cluster.reserve(1, &owned(1, "a"));
+
+        assert_eq!(
+            LeastLoaded.place(&owned(2, "a"), &cluster),
+            Err(Rejection::QuotaExceeded { process_id: 2, tenant: "a".to_string() })
+        );
+        assert!(LeastLoaded.place(&owned(3, "b"), &cluster).is_ok());
+    }
+}
@@ -1,49 +1,572 @@
-use crate::node::{Node};
-use crate::process::Process;
+use crate::node::{Node, NodeStatus};
+use crate::process::{Process, ProcessOutput, ProcessState};
 use crate::resource::Resources;
-use crate::message::{Vote, Action, Request};
+use crate::message::{Vote, Action, ActionOutcome, Request, Message, Envelope};
+use crate::fault::{Fault, FaultInjector, MessageFault};
+use crate::clock::Clock;
+use crate::network::Network;
+use crate::migration::{Migration, MigrationConfig, MigrationPhase};
+use crate::drain::DrainState;
+use crate::join::{JoinError, JoinStatus};
+use crate::membership::MembershipEvent;
+use crate::recovery::{RecoveryOutcome, RecoveryPolicy, RecoveryState};
+use crate::deadline::{edf_order, remaining_time, AdmissionError, DeadlineMiss};
+use crate::job::Job;
+use crate::affinity::is_allowed;
+use crate::rebalance::{imbalance, node_load, Rebalancer};
+use crate::autoscale::{Autoscaler, ScalingEvent};
+use crate::stealing::{Steal, WorkStealing};
+use crate::backfill::{min_resources, reservation_for, Reservation};
+use crate::tenant::{tenant_of, tenant_usage, within_quota};
+use crate::task::{Task, Workflow, WorkflowReport, WorkflowStatus};
+use crate::scheduler::{ClusterSnapshot, LeastLoaded, NodeSnapshot, Rejection, Scheduler};
+use crate::runner::RunningCommand;
+use std::cmp::Ordering;
 use std::collections::{HashMap, VecDeque};
+use std::io;
+
+// What to do with the processes of a failed node that no surviving node can host
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrphanPolicy {
+    // Keep them pending until capacity frees up
+    Queue,
+    // Drop the lowest priority ones (see `Session::shed_processes`)
+    ShedLowestPriority,
+    // Add a new node contributing the given resources
+    ScaleOut { node_resources: Resources },
+}
+
+// Order of the pending queue
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueueOrder {
+    // Highest priority first
+    Priority,
+    // Earliest deadline first
+    EarliestDeadlineFirst,
+}
+
+impl QueueOrder {
+    pub fn compare(&self, a: &Process, b: &Process) -> Ordering {
+        match self {
+            QueueOrder::Priority => b.priority.cmp(&a.priority),
+            QueueOrder::EarliestDeadlineFirst => edf_order(a, b),
+        }
+    }
+
+    // A job is queued where its most urgent process would be, None when it has no processes
+    pub fn job_lead<'a>(&self, job: &'a Job) -> Option<&'a Process> {
+        job.processes.iter().min_by(|a, b| self.compare(a, b))
+    }
+}
 
 pub struct Session {
     pub nodes: Vec<Node>,
     pub processes: Vec<Process>,
+    // Cluster totals, aggregated from the nodes' own capacity
     pub total_resources: Resources,
     pub available_resources: Resources,
-    pub pending_votes: HashMap<Action, Vec<(u32, Vote)>>,
+    // Placement strategy for pending processes
+    pub scheduler: Box<dyn Scheduler>,
+    // Whether a process that doesn't fit may evict lower priority running processes
+    pub preemption: bool,
+    pub queue_order: QueueOrder,
+    // EASY backfilling: capacity is held for the first blocked process and the ones behind it
+    // only start if they don't delay it. Disabled by default.
+    pub backfilling: bool,
+    pub reservation: Option<Reservation>,
+    // Deadlines that were missed, in detection order
+    pub missed_deadlines: Vec<DeadlineMiss>,
+    // Most resources each tenant may use at once, tenants without quota are unlimited
+    pub quotas: HashMap<String, Resources>,
+    // Actions being voted on, with their proposer and the votes received so far
+    pub pending_votes: HashMap<Action, (u32, Vec<(u32, Vote)>)>,
     // Mutual exclusion queues
     pub request_queue: VecDeque<Request>,
     pub deferred_replies: HashMap<u32, Vec<Request>>,
     pub replies_received: HashMap<u32, Vec<u32>>,
+    pub clock: Clock,
+    // Actions executed after reaching consensus, in order
+    pub committed_actions: Vec<Action>,
+    // Node recovery workflow
+    pub recovery_policy: RecoveryPolicy,
+    pub recoveries: HashMap<u32, RecoveryState>,
+    // Simulated network, can be partitioned
+    pub network: Network,
+    // Chaos testing, disabled when None
+    pub fault_injector: Option<FaultInjector>,
+    // Idle nodes take queued processes from other nodes' local queues, disabled when None
+    pub work_stealing: Option<WorkStealing>,
+    // Moves processes off the busiest nodes over time, disabled when None
+    pub rebalancer: Option<Rebalancer>,
+    // Adds and removes nodes following the demand, disabled when None
+    pub autoscaler: Option<Autoscaler>,
+    // Live process migrations, finished ones are kept as history
+    pub migrations: Vec<Migration>,
+    pub migration_config: MigrationConfig,
+    // Rescheduling after node failures
+    pub orphan_policy: OrphanPolicy,
+    pub shed_processes: Vec<Process>,
+    // Processes that succeeded, failed or were cancelled
+    pub finished_processes: Vec<Process>,
+    // OS commands being run for processes (see `runner::run_process`), removing one kills it
+    pub running_commands: HashMap<u32, RunningCommand>,
+    // Nodes being drained before their removal
+    pub drains: HashMap<u32, DrainState>,
+    // Nodes waiting for the admission vote
+    pub pending_joins: HashMap<u32, Node>,
+    // Task DAGs whose ready tasks are turned into processes
+    pub workflows: Vec<Workflow>,
+    // Last id given to a process created by the session, ids are never reused
+    pub last_process_id: u32,
+    // Gangs of processes waiting to be placed together
+    pub jobs: Vec<Job>,
 }
 
 impl Session {
-    pub fn new(nodes: Vec<Node>, processes: Vec<Process>, total_resources: Resources) -> Self {
-        Session {
+    // Nodes created without capacity get an even share of `total_resources`
+    pub fn new(mut nodes: Vec<Node>, processes: Vec<Process>, total_resources: Resources) -> Self {
+        let unsized_nodes: Vec<usize> = (0..nodes.len())
+            .filter(|&i| nodes[i].total_resources.is_zero())
+            .collect();
+        let shares = split_resources(&total_resources, unsized_nodes.len());
+        for (i, share) in unsized_nodes.into_iter().zip(shares) {
+            nodes[i].total_resources = share.clone();
+            nodes[i].available_resources = share;
+        }
+
+        let mut session = Session {
             nodes,
             processes,
-            total_resources: total_resources.clone(),
-            available_resources: total_resources,
+            total_resources: Resources::new(0, 0, 0),
+            available_resources: Resources::new(0, 0, 0),
+            scheduler: Box::new(LeastLoaded),
+            preemption: true,
+            queue_order: QueueOrder::Priority,
+            backfilling: false,
+            reservation: None,
+            missed_deadlines: Vec::new(),
+            quotas: HashMap::new(),
             pending_votes: HashMap::new(),
             request_queue: VecDeque::new(),
             deferred_replies: HashMap::new(),
             replies_received: HashMap::new(),
-        }
+            clock: Clock::System,
+            committed_actions: Vec::new(),
+            recovery_policy: RecoveryPolicy::default(),
+            recoveries: HashMap::new(),
+            network: Network::new(),
+            fault_injector: None,
+            work_stealing: None,
+            rebalancer: None,
+            autoscaler: None,
+            migrations: Vec::new(),
+            migration_config: MigrationConfig::default(),
+            orphan_policy: OrphanPolicy::Queue,
+            shed_processes: Vec::new(),
+            finished_processes: Vec::new(),
+            running_commands: HashMap::new(),
+            drains: HashMap::new(),
+            pending_joins: HashMap::new(),
+            workflows: Vec::new(),
+            last_process_id: 0,
+            jobs: Vec::new(),
+        };
+        session.refresh_resources();
+        session
     }
 
+    // Add a node directly, without the join protocol (see `request_join`).
+    // The node keeps the capacity it was created with.
     pub fn add_node(&mut self, node: Node) {
         self.nodes.push(node);
+        self.refresh_resources();
     }
 
     pub fn remove_node(&mut self, node_id: u32) {
+        // Let the rest of the cluster know through gossip
+        let now = self.clock.now();
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.id == node_id) {
+            if let Some(&peer) = node.membership.live_members().first() {
+                node.membership.announce_leave();
+                self.swim_ping(node_id, peer, now);
+            }
+        }
+
+        // Processes that never started on the node go back to the cluster queue
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.id == node_id) {
+            let queued: Vec<Process> = node.local_queue.drain(..).collect();
+            for process in queued {
+                self.requeue(process);
+            }
+        }
+
         self.nodes.retain(|node| node.id != node_id);
+        for node in self.nodes.iter_mut() {
+            node.failure_detector.remove(node_id);
+        }
+        self.refresh_resources();
+    }
+
+    // Recompute the cluster totals from the nodes
+    fn refresh_resources(&mut self) {
+        self.total_resources = self.nodes.iter().map(|n| &n.total_resources).sum();
+        self.available_resources = self.nodes.iter().map(|n| &n.available_resources).sum();
+    }
+
+    // Broadcast a heartbeat from a node to every other node
+    pub fn record_heartbeat(&mut self, node_id: u32) {
+        let now = self.clock.now();
+        for node in self.nodes.iter_mut() {
+            if node.id == node_id {
+                node.last_heartbeat = now;
+                node.heartbeat_since_reinstall = true;
+            } else if self.network.can_reach(node_id, node.id) {
+                node.observe_heartbeat(node_id, now);
+            }
+        }
+    }
+
+    // Average suspicion level the other nodes have about a node
+    pub fn suspicion_level(&self, node_id: u32) -> Option<f64> {
+        let now = self.clock.now();
+        let levels: Vec<f64> = self
+            .nodes
+            .iter()
+            .filter(|node| node.id != node_id)
+            .filter_map(|node| node.failure_detector.phi_at(node_id, now))
+            .collect();
+
+        if levels.is_empty() {
+            None
+        } else {
+            Some(levels.iter().sum::<f64>() / levels.len() as f64)
+        }
     }
 
-    // Reinstalls a failure node (if possible)
+    // Reinstalls a failure node (if possible). The node stays Recovering until a recovery attempt
+    // (see `advance_recoveries`) finds it healthy.
     pub fn try_to_reinstall_node(&mut self, node_id: u32) {
-        println!("Trying to reinstall node {}", node_id);
-        let new_node = Node::new(node_id);
-        self.add_node(new_node);
-        println!("Node {} reinstalled.", node_id);
+        let first_attempt_at = self.clock.now() + self.recovery_policy.initial_backoff;
+        let state = self
+            .recoveries
+            .entry(node_id)
+            .or_insert_with(|| RecoveryState::new(node_id, Vec::new(), Resources::new(0, 0, 0), first_attempt_at));
+        println!("Trying to reinstall node {}, first attempt at {}", node_id, state.next_attempt_at);
+
+        if !self.nodes.iter().any(|n| n.id == node_id) {
+            let mut new_node = Node::with_resources(node_id, self.recoveries[&node_id].capacity.clone());
+            new_node.status = NodeStatus::Recovering;
+            self.add_node(new_node);
+        }
+    }
+
+    // Run the recovery attempts whose backoff has expired
+    pub fn advance_recoveries(&mut self) -> Vec<(u32, RecoveryOutcome)> {
+        let now = self.clock.now();
+        let mut due: Vec<u32> = self
+            .recoveries
+            .values()
+            .filter(|r| r.next_attempt_at <= now)
+            .map(|r| r.node_id)
+            .collect();
+        due.sort();
+
+        due.into_iter()
+            .filter_map(|node_id| self.attempt_recovery(node_id).map(|outcome| (node_id, outcome)))
+            .collect()
+    }
+
+    // One recovery attempt: catch up with the cluster, then run the health checks
+    pub fn attempt_recovery(&mut self, node_id: u32) -> Option<RecoveryOutcome> {
+        let now = self.clock.now();
+        let mut state = self.recoveries.remove(&node_id)?;
+        state.attempts += 1;
+        println!("Recovery attempt {} for node {}", state.attempts, node_id);
+
+        self.catch_up_node(node_id, &state.former_assignments);
+
+        let health_check = self.recovery_policy.health_check;
+        let healthy = self
+            .nodes
+            .iter()
+            .find(|n| n.id == node_id)
+            .map(health_check)
+            .unwrap_or(false)
+            && self.has_quorum(node_id);
+
+        if healthy {
+            if let Some(node) = self.nodes.iter_mut().find(|n| n.id == node_id) {
+                node.status = NodeStatus::Active;
+            }
+            println!("Node {} reinstalled.", node_id);
+            Some(RecoveryOutcome::Recovered)
+        } else if state.attempts >= self.recovery_policy.max_attempts {
+            println!("Node {} failed its health checks {} times, giving up.", node_id, state.attempts);
+            self.remove_node(node_id);
+            Some(RecoveryOutcome::GaveUp)
+        } else {
+            state.next_attempt_at = now + self.recovery_policy.backoff(state.attempts);
+            println!(
+                "Node {} failed its health checks, next attempt at {}",
+                node_id, state.next_attempt_at
+            );
+            let outcome = RecoveryOutcome::Retrying { next_attempt_at: state.next_attempt_at };
+            self.recoveries.insert(node_id, state);
+            Some(outcome)
+        }
+    }
+
+    // Bring a recovering node up to date: membership, committed actions and former assignments
+    fn catch_up_node(&mut self, node_id: u32, former_assignments: &[u32]) {
+        let index = match self.nodes.iter().position(|n| n.id == node_id && n.status == NodeStatus::Recovering) {
+            Some(index) => index,
+            None => return,
+        };
+
+        let mut node = self.nodes.remove(index);
+        self.share_cluster_state(&mut node);
+        // Former processes the cluster took over meanwhile (rescheduled, queued or finished) must not run twice
+        let taken_over: Vec<u32> = former_assignments
+            .iter()
+            .copied()
+            .filter(|&id| self.all_processes().any(|p| p.id == id))
+            .collect();
+        let dropped = node.drop_processes(&taken_over);
+        let incarnation = node.membership.incarnation;
+        self.nodes.insert(index, node);
+
+        for process in dropped {
+            println!("Node {} drops process {}, the cluster took it over", node_id, process.id);
+            self.deallocate_resources(node_id, &process.needed_resources);
+        }
+        self.announce_member(node_id, incarnation);
+    }
+
+    // Hand the current cluster state (membership and committed actions) to a node
+    fn share_cluster_state(&self, node: &mut Node) {
+        let now = self.clock.now();
+
+        // Membership and last known incarnation from the peers
+        let mut members: Vec<u32> = Vec::new();
+        let mut incarnation = 0;
+        for peer in self
+            .nodes
+            .iter()
+            .filter(|n| n.id != node.id && n.status == NodeStatus::Active && self.network.can_reach(node.id, n.id))
+        {
+            members.push(peer.id);
+            members.extend(peer.membership.live_members());
+            if let Some(member) = peer.membership.members.get(&node.id) {
+                incarnation = incarnation.max(member.incarnation);
+            }
+        }
+        members.sort();
+        members.dedup();
+
+        // Supersede any failure report about a previous incarnation
+        node.membership.rejoin(incarnation);
+        for member in members {
+            node.membership.add_seed(member, now);
+        }
+
+        for action in self.committed_actions.iter() {
+            node.known_actions.insert(action.clone(), true);
+        }
+        node.caught_up = true;
+    }
+
+    // Make the reachable peers aware of a (re)joining node right away
+    fn announce_member(&mut self, node_id: u32, incarnation: u64) {
+        let now = self.clock.now();
+        let join = MembershipEvent::Join { node_id, incarnation };
+        let network = &self.network;
+        for peer in self
+            .nodes
+            .iter_mut()
+            .filter(|n| n.id != node_id && n.status == NodeStatus::Active && network.can_reach(node_id, n.id))
+        {
+            peer.membership.apply(join.clone(), now);
+        }
+    }
+
+    // Join protocol
+
+    // A new node announces itself; the members vote on its admission
+    pub fn request_join(&mut self, node: Node) -> Result<JoinStatus, JoinError> {
+        let node_id = node.id;
+        println!("Node {} asks to join the cluster", node_id);
+
+        if self.nodes.iter().any(|n| n.id == node_id) {
+            return Err(JoinError::DuplicateId { node_id });
+        }
+        if self.recoveries.contains_key(&node_id) {
+            return Err(JoinError::ConflictingId { node_id, reason: "a node being recovered".to_string() });
+        }
+        if self.pending_joins.contains_key(&node_id) {
+            return Err(JoinError::ConflictingId { node_id, reason: "another pending join".to_string() });
+        }
+
+        self.pending_joins.insert(node_id, node);
+
+        // The first member able to take decisions sponsors the admission
+        let sponsor = self
+            .nodes
+            .iter()
+            .filter(|n| n.status == NodeStatus::Active)
+            .map(|n| n.id)
+            .find(|&id| self.has_quorum(id));
+        let action = Action::AdmitNode { node_id };
+        match sponsor {
+            Some(sponsor_id) => self.initiate_voting(sponsor_id, action.clone()),
+            // Bootstrapping an empty cluster
+            None if self.nodes.is_empty() => self.admit_node(node_id),
+            // Nothing was proposed, the members didn't reject the node
+            None => {
+                self.pending_joins.remove(&node_id);
+                return Err(JoinError::NoQuorum { node_id });
+            }
+        }
+
+        if self.nodes.iter().any(|n| n.id == node_id) {
+            Ok(JoinStatus::Admitted)
+        } else if self.pending_votes.contains_key(&action) {
+            Ok(JoinStatus::Pending)
+        } else {
+            self.pending_joins.remove(&node_id);
+            Err(JoinError::Rejected { node_id })
+        }
+    }
+
+    // Add an admitted node along with the current cluster state
+    fn admit_node(&mut self, node_id: u32) {
+        let mut node = match self.pending_joins.remove(&node_id) {
+            Some(node) => node,
+            None => return,
+        };
+
+        self.share_cluster_state(&mut node);
+        let incarnation = node.membership.incarnation;
+        self.add_node(node);
+        self.announce_member(node_id, incarnation);
+        println!("Node {} admitted to the cluster", node_id);
+    }
+
+    // Membership (SWIM protocol)
+
+    // Run one protocol period: every running node probes one member
+    pub fn swim_round(&mut self) {
+        let now = self.clock.now();
+        let running: Vec<u32> = self
+            .nodes
+            .iter()
+            .filter(|n| n.status != NodeStatus::Halted)
+            .map(|n| n.id)
+            .collect();
+
+        for node_id in running.iter() {
+            self.swim_probe(*node_id, now);
+        }
+
+        for node in self.nodes.iter_mut().filter(|n| running.contains(&n.id)) {
+            node.membership.expire_suspects(now);
+        }
+    }
+
+    fn swim_probe(&mut self, prober_id: u32, now: u64) {
+        let prober = match self.nodes.iter_mut().find(|n| n.id == prober_id) {
+            Some(node) => node,
+            None => return,
+        };
+
+        // A node that doesn't know anybody yet joins through a seed node
+        if prober.membership.members.is_empty() {
+            prober.membership.announce_join();
+            let seed = self
+                .nodes
+                .iter()
+                .map(|n| n.id)
+                .find(|&id| id != prober_id && self.can_communicate(prober_id, id));
+            if let Some(seed) = seed {
+                println!("Node {} joining the cluster through node {}", prober_id, seed);
+                self.swim_ping(prober_id, seed, now);
+            }
+            return;
+        }
+
+        let target = match prober.membership.next_probe_target() {
+            Some(target) => target,
+            None => return,
+        };
+
+        // Direct probe
+        if self.swim_ping(prober_id, target, now) {
+            return;
+        }
+
+        // Indirect probe through other members
+        let helpers = match self.nodes.iter_mut().find(|n| n.id == prober_id) {
+            Some(node) => node.membership.indirect_probe_helpers(target),
+            None => return,
+        };
+        for helper in helpers {
+            if self.swim_ping(prober_id, helper, now) && self.swim_ping(helper, target, now) {
+                println!("Node {} reached node {} through node {}", prober_id, target, helper);
+                return;
+            }
+        }
+
+        println!("Node {} suspects node {}", prober_id, target);
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.id == prober_id) {
+            node.membership.suspect(target, now);
+        }
+    }
+
+    // Ping/ack exchange between two nodes, both piggybacking their gossip.
+    // Returns false if the target didn't answer.
+    fn swim_ping(&mut self, from_node_id: u32, to_node_id: u32, now: u64) -> bool {
+        if !self.can_communicate(from_node_id, to_node_id) {
+            return false;
+        }
+
+        let (updates, incarnation) = match self.nodes.iter_mut().find(|n| n.id == from_node_id) {
+            Some(node) => (node.membership.piggyback(), node.membership.incarnation),
+            None => return false,
+        };
+
+        let (reply, reply_incarnation) = match self.nodes.iter_mut().find(|n| n.id == to_node_id) {
+            Some(node) => {
+                node.membership.record_contact(from_node_id, incarnation, now);
+                node.membership.apply_all(updates, now);
+                (node.membership.piggyback(), node.membership.incarnation)
+            }
+            None => return false,
+        };
+
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.id == from_node_id) {
+            node.membership.record_contact(to_node_id, reply_incarnation, now);
+            node.membership.apply_all(reply, now);
+        }
+        true
+    }
+
+    // Whether a message can travel between two nodes
+    pub fn can_communicate(&self, from_node_id: u32, to_node_id: u32) -> bool {
+        let is_running = |id: u32| {
+            self.nodes
+                .iter()
+                .any(|n| n.id == id && n.status != NodeStatus::Halted)
+        };
+        is_running(from_node_id) && is_running(to_node_id) && self.network.can_reach(from_node_id, to_node_id)
+    }
+
+    // Whether a node is on the side of the network holding a majority of the cluster
+    pub fn has_quorum(&self, node_id: u32) -> bool {
+        let reachable = self
+            .nodes
+            .iter()
+            .filter(|n| self.network.can_reach(node_id, n.id))
+            .count();
+        reachable > self.nodes.len() / 2
     }
 
     // Resource access (Ricart-Agrawala algorithm)    
@@ -61,11 +584,11 @@ impl Session {
         // Add the request to the global queue
         self.request_queue.push_back(request.clone());
     
-        // Collect the IDs of other nodes
+        // Collect the IDs of other nodes (only those the request can reach)
         let other_node_ids: Vec<u32> = self
             .nodes
             .iter()
-            .filter(|node| node.id != node_id)
+            .filter(|node| node.id != node_id && self.network.can_reach(node_id, node.id))
             .map(|node| node.id)
             .collect();
     
@@ -81,9 +604,7 @@ impl Session {
     // Generate a logical timestamp
     fn generate_timestamp(&self) -> u64 {
         // Simple logical clock
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        duration.as_millis() as u64
+        self.clock.now()
     }
 
     // Handle an incoming request
@@ -98,7 +619,7 @@ impl Session {
             // Defer the reply
             self.deferred_replies
                 .entry(to_node_id)
-                .or_insert(Vec::new())
+                .or_default()
                 .push(request);
         }
     }
@@ -123,11 +644,16 @@ impl Session {
     pub fn send_reply(&mut self, to_node_id: u32, from_node_id: u32) {
         println!("Node {} sends reply to Node {}", from_node_id, to_node_id);
 
-        // Record that we have replied to this node
-        self.replies_received
-            .entry(to_node_id)
-            .or_insert(Vec::new())
-            .push(from_node_id);
+        self.send_message(from_node_id, to_node_id, Message::Reply);
+    }
+
+    // Record a reply received by a node
+    fn receive_reply(&mut self, to_node_id: u32, from_node_id: u32) {
+        let replies = self.replies_received.entry(to_node_id).or_default();
+        // Duplicated replies must not count twice
+        if !replies.contains(&from_node_id) {
+            replies.push(from_node_id);
+        }
     }
 
     // Check if the node can access the resource
@@ -158,407 +684,3513 @@ impl Session {
         self.replies_received.remove(&node_id);
     }
 
-    // Allocate resources for a process
-    pub fn allocate_resources(&mut self, needed_resources: &Resources) -> bool {
-        if self.available_resources.can_allocate(needed_resources) {
-            self.available_resources.allocate(needed_resources);
-            println!("Resources allocated: {:?}", needed_resources);
-            true
+    // Allocate resources for a process on a node
+    pub fn allocate_resources(&mut self, node_id: u32, needed_resources: &Resources) -> bool {
+        let allocated = match self.nodes.iter_mut().find(|n| n.id == node_id) {
+            Some(node) => node.allocate(needed_resources),
+            None => false,
+        };
+        if allocated {
+            println!("Resources allocated on node {}: {:?}", node_id, needed_resources);
+            self.refresh_resources();
         } else {
-            println!("Not enough resources available to allocate: {:?}", needed_resources);
-            false
+            println!("Not enough resources available on node {} to allocate: {:?}", node_id, needed_resources);
         }
+        allocated
     }
 
-    // Deallocate resources after process completion
-    pub fn deallocate_resources(&mut self, used_resources: &Resources) {
-        self.available_resources.deallocate(used_resources);
-        println!("Resources deallocated: {:?}", used_resources);
+    // Deallocate resources of a node after process completion
+    pub fn deallocate_resources(&mut self, node_id: u32, used_resources: &Resources) {
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.id == node_id) {
+            node.deallocate(used_resources);
+            println!("Resources deallocated on node {}: {:?}", node_id, used_resources);
+        }
+        self.refresh_resources();
     }    
 
+    // Queue a gang of processes to be placed all together
+    pub fn submit_job(&mut self, job: Job) {
+        println!("Job {} submitted with {} processes", job.id, job.processes.len());
+        self.jobs.push(job);
+    }
+
+    // Place the pending jobs and processes following the queue order. Each job, all or nothing, is placed
+    // after the pending processes ordered before it and ahead of its equals, since jobs are the hardest to fit.
+    // The pending processes between two jobs are placed together, the scheduler sees them at once.
     pub async fn assign_processes(&mut self) {
-        let mut waiting_queue: Vec<Process> = vec![];
-        for process in self.processes.clone() {
-            // Step 1: Find the node with the least active processes
-            let node_id = match self.nodes.iter()
-                .min_by_key(|node| node.active_processes.len())
-                .map(|node| node.id) 
-            {
-                Some(id) => id,
-                None => {
-                    eprintln!("No nodes available to assign process {}", process.id);
-                    waiting_queue.push(process);
-                    continue;
-                }
+        self.check_deadlines();
+        self.reservation = None;
+        self.sort_pending();
+        let order = self.queue_order.clone();
+        let mut jobs = std::mem::take(&mut self.jobs);
+        jobs.sort_by(|a, b| match (order.job_lead(a), order.job_lead(b)) {
+            (Some(a), Some(b)) => order.compare(a, b),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        });
+
+        let mut pending = std::mem::take(&mut self.processes);
+        let mut waiting_queue = Vec::new();
+        for job in jobs {
+            let ahead = match order.job_lead(&job) {
+                Some(lead) => pending.iter().take_while(|p| order.compare(p, lead) == Ordering::Less).count(),
+                None => 0,
             };
+            let behind = pending.split_off(ahead);
+            waiting_queue.extend(self.place_queue(pending));
+            pending = behind;
 
-            // Step 2: Allocate resources
-            if self.allocate_resources(&process.needed_resources) {
-                // Step 3: Assign the process to the node
-                if let Some(node) = self.nodes.iter_mut().find(|node| node.id == node_id) {
-                    node.active_processes.push(process.clone());
-                    println!("Assigned process {} to node {}", process.id, node.id);
-                } else {
-                    eprintln!("Node with id {} not found", node_id);
-                    waiting_queue.push(process);
-                }
-            } else {
-                eprintln!(
-                    "Failed to assign process {} due to insufficient resources.",
-                    process.id
-                );
-                waiting_queue.push(process);
+            // A job can't backfill, it waits while capacity is held for a blocked process
+            if self.reservation.is_some() || !self.place_job(&job) {
+                self.jobs.push(job);
             }
         }
+        waiting_queue.extend(self.place_queue(pending));
 
-        // Re-assign the waiting queue
-        self.processes = waiting_queue;
+        // Re-assign the waiting queue, preempted processes are already in it
+        self.processes.extend(waiting_queue);
+        self.sort_pending();
     }
 
-    // Initiate voting on an action proposed by a node
-    pub fn initiate_voting(&mut self, proposer_id: u32, action: Action) {
-        println!(
-            "Session initiating voting on action {:?} proposed by Node {}",
-            action, proposer_id
-        );
-
-        // Record that the proposer has voted for the action
-        self.pending_votes
-            .insert(action.clone(), vec![(proposer_id, Vote::Approve)]);
+    // Place pending processes, returns the ones that have to wait
+    fn place_queue(&mut self, pending: Vec<Process>) -> Vec<Process> {
+        if pending.is_empty() {
+            Vec::new()
+        } else if self.backfilling {
+            self.backfill(pending)
+        } else {
+            self.place_pending(pending)
+        }
+    }
 
-        // Collect votes from other nodes
-        let mut collected_votes = vec![];
+    // Let the scheduler place the whole queue at once, returns the processes that didn't fit
+    fn place_pending(&mut self, pending: Vec<Process>) -> Vec<Process> {
+        let mut cluster = self.cluster_snapshot();
+        let placements = self.scheduler.place_batch(&pending, &mut cluster);
 
-        for node in self
-            .nodes
-            .iter_mut()
-            .filter(|n| n.id != proposer_id)
-        {
-            let vote = node.receive_proposal(action.clone());
-            collected_votes.push((node.id, vote));
+        let mut rejected: Vec<Process> = vec![];
+        for (process, placement) in pending.into_iter().zip(placements) {
+            let placed = match placement {
+                Ok(node_id) => self.bind_process(node_id, &process),
+                Err(rejection) => {
+                    eprintln!("Failed to assign process {}: {}", process.id, rejection);
+                    false
+                }
+            };
+            if !placed {
+                rejected.push(process);
+            }
         }
 
-        // Process votes after the loop
-        for (node_id, vote) in collected_votes {
-            self.cast_vote(node_id, action.clone(), vote);
+        // Once the regular placements are done, make room for the rejected ones if possible,
+        // or let them wait on a node
+        let mut waiting = Vec::new();
+        for process in rejected {
+            if self.preempt_for(&process) {
+                continue;
+            }
+            waiting.extend(self.queue_locally(process));
         }
-
-        // Check if consensus is reached
-        self.check_consensus(action);
+        waiting
     }
 
-    // Nodes call this method to cast their vote
-    pub fn cast_vote(&mut self, node_id: u32, action: Action, vote: Vote) {
-        let votes = self
-            .pending_votes
-            .entry(action.clone())
-            .or_insert(Vec::new());
-        votes.push((node_id, vote.clone()));
-        println!("Node {} voted {:?} for action {:?}", node_id, vote, action);
+    // With work stealing, a process no node has room for waits in the local queue of the node the scheduler
+    // would pick if the nodes were empty, idle nodes may steal it from there.
+    // Returns the process when it stays in the cluster queue.
+    fn queue_locally(&mut self, process: Process) -> Option<Process> {
+        if self.work_stealing.is_none() {
+            return Some(process);
+        }
+        let mut cluster = self.cluster_snapshot();
+        for node in cluster.nodes.iter_mut() {
+            node.available_resources = node.total_resources.clone();
+        }
+        match self.scheduler.place(&process, &cluster) {
+            Ok(node_id) => {
+                self.dispatch_process(node_id, process);
+                None
+            }
+            Err(_) => Some(process),
+        }
     }
 
-    // Check if the action has received enough votes
-    fn check_consensus(&mut self, action: Action) {
-        if let Some(votes) = self.pending_votes.get(&action) {
-            let total_nodes = self.nodes.len();
+    // Place the queue in order. The first process that doesn't fit gets a reservation on the node where it
+    // can start the earliest; the processes behind it may only use that node if they end before the
+    // reserved start time or fit in what the reserved process leaves free. Only the first blocked
+    // process may preempt, the ones behind it wait.
+    // Processes go through `Scheduler::place` one at a time, so the batch ordering of `place_batch`
+    // (bin packing, DRF) doesn't apply while backfilling: the reservation depends on the queue order.
+    fn backfill(&mut self, pending: Vec<Process>) -> Vec<Process> {
+        let now = self.clock.now();
 
-            let approvals = votes
-                .iter()
-                .filter(|&(_, ref v)| *v == Vote::Approve)
-                .count();
+        let mut rejected: Vec<Process> = vec![];
+        for process in pending {
+            let mut cluster = self.cluster_snapshot();
+            let limited = self.reservation.as_ref().filter(|r| !r.finishes_before(&process, now)).cloned();
+            if let Some(reservation) = &limited {
+                if let Some(node) = cluster.nodes.iter_mut().find(|n| n.id == reservation.node_id) {
+                    node.available_resources = min_resources(&node.available_resources, &reservation.extra);
+                }
+            }
 
-            if approvals > total_nodes / 2 {
-                println!("Consensus reached on action {:?}", action);
-                self.execute_action(action.clone());
-                self.pending_votes.remove(&action);
-            } else if votes.len() - approvals > total_nodes / 2 {
-                println!("Consensus rejected on action {:?}", action);
-                self.pending_votes.remove(&action);
+            let placed = match self.scheduler.place(&process, &cluster) {
+                Ok(node_id) => self.bind_process(node_id, &process),
+                Err(rejection) => {
+                    eprintln!("Failed to assign process {}: {}", process.id, rejection);
+                    if self.reservation.is_some() {
+                        false
+                    } else if self.preempt_for(&process) {
+                        true
+                    } else {
+                        if !matches!(rejection, Rejection::QuotaExceeded { .. }) {
+                            self.reserve_for(&process, now);
+                        }
+                        false
+                    }
+                }
+            };
+            if !placed {
+                rejected.push(process);
+                continue;
+            }
+
+            if let Some(reservation) = self.reservation.as_mut() {
+                println!("Backfilling process {} ahead of process {}", process.id, reservation.process_id);
+                let on_reserved_node = self.nodes.iter()
+                    .find(|n| n.id == reservation.node_id)
+                    .is_some_and(|n| n.active_processes.iter().any(|p| p.id == process.id));
+                if limited.is_some() && on_reserved_node {
+                    reservation.extra.allocate(&process.needed_resources);
+                }
             }
-            // Else, keep waiting for more votes
         }
+        rejected
     }
 
-    // Execute the action once consensus is reached
-    fn execute_action(&mut self, action: Action) {
-        match action {
-            Action::ProcessFailure { node_id, reason } => {
-                println!("Executing ProcessFailure action for node {}: {}", node_id, reason);
-                self.handle_node_failure(node_id, reason);
-            },
-            Action::RedistributeProcess { process_id } => {
-                println!("Executing RedistributeProcess action for process {}", process_id);
-                // TODO: Implement redistribution logic here
-            },
-            Action::NodeFailure { node_id, reason } => {
-                println!("Executing NodeFailure action for node {}: {}", node_id, reason);
-                self.handle_node_failure(node_id, reason);
-            },
-            // Handle other actions
+    // Hold capacity for a blocked process where it can start the earliest
+    fn reserve_for(&mut self, process: &Process, now: u64) {
+        let cluster = self.cluster_snapshot();
+        let nodes: Vec<&Node> = self.nodes.iter().filter(|n| cluster.nodes.iter().any(|c| c.id == n.id)).collect();
+        self.reservation = reservation_for(process, &nodes, now);
+        match &self.reservation {
+            Some(reservation) => println!(
+                "Reserved node {} at {} for process {}",
+                reservation.node_id, reservation.start_time, process.id
+            ),
+            None => println!("No projected start for process {}, nothing reserved", process.id),
         }
     }
 
-    pub fn handle_node_failure(&mut self, node_id: u32, reason: String) {
-        println!("Handling failure of node {}: {}", node_id, reason);
+    // Order the pending queue, keeping the arrival order between equals
+    fn sort_pending(&mut self) {
+        let order = self.queue_order.clone();
+        self.processes.sort_by(|a, b| order.compare(a, b));
+    }
 
-        //Finds the node failure
-        if let Some(node_index) = self.nodes.iter().position(|n| n.id == node_id) {
-            // Extract active processes
-            let active_processes = self.nodes[node_index].active_processes.clone();
-        
-            // Deallocate resources
-            for process in &active_processes {
-                self.deallocate_resources(&process.needed_resources);
+    // Local queues and work stealing
+
+    // Queue a process on a specific node, it starts there once the node has room
+    pub fn dispatch_process(&mut self, node_id: u32, mut process: Process) {
+        let now = self.clock.now();
+        match self.nodes.iter_mut().find(|n| n.id == node_id) {
+            Some(node) => {
+                if let Err(error) = process.transition(ProcessState::Scheduled, now) {
+                    eprintln!("{}, not queued on node {}", error, node_id);
+                    return;
+                }
+                println!("Process {} queued on node {}", process.id, node_id);
+                node.local_queue.push_back(process);
             }
-        
-            // Redistribute processes
-            for process in active_processes {
+            None => {
+                eprintln!("Node {} not found, process {} goes to the cluster queue", node_id, process.id);
                 self.processes.push(process);
+                self.sort_pending();
             }
-        
-            // Remove the node
-            self.remove_node(node_id);
-            println!("Node {} deleted. Processes reassigned.", node_id);
-        
-            // Optionally: Try to reinstall the node
-            self.try_to_reinstall_node(node_id);
         }
-        
     }
 
-    // Method to get total number of nodes
-    pub fn total_nodes(&self) -> usize {
-        self.nodes.len()
-    }    
+    // Start the queued processes of every schedulable node in order, until one doesn't fit
+    pub fn start_queued_processes(&mut self) {
+        let node_ids: Vec<u32> = self.cluster_snapshot().nodes.iter().map(|n| n.id).collect();
+        for node_id in node_ids {
+            loop {
+                let node = self.nodes.iter_mut().find(|n| n.id == node_id).unwrap();
+                let process = match node.local_queue.front() {
+                    Some(process) if node.can_host(&process.needed_resources) => node.local_queue.pop_front().unwrap(),
+                    _ => break,
+                };
+                if !self.bind_process(node_id, &process) {
+                    let node = self.nodes.iter_mut().find(|n| n.id == node_id).unwrap();
+                    node.local_queue.push_front(process);
+                    break;
+                }
+            }
+        }
+    }
 
+    // Every idle node (nothing queued locally, room left) tries random victims until it finds a queued process
+    // it can run, taken from the back of the victim's queue. Returns the steals of this round.
+    pub fn steal_work(&mut self) -> Vec<Steal> {
+        let mut stealing = match self.work_stealing.take() {
+            Some(stealing) => stealing,
+            None => return Vec::new(),
+        };
+        let now = self.clock.now();
+        let schedulable: Vec<u32> = self.cluster_snapshot().nodes.iter().map(|n| n.id).collect();
+        let idle: Vec<u32> = self
+            .nodes
+            .iter()
+            .filter(|n| schedulable.contains(&n.id) && n.is_idle())
+            .map(|n| n.id)
+            .collect();
+
+        let mut steals = Vec::new();
+        for thief_id in idle {
+            let mut victims: Vec<u32> = self
+                .nodes
+                .iter()
+                .filter(|n| n.id != thief_id && schedulable.contains(&n.id))
+                .filter(|n| n.local_queue.len() >= stealing.min_queue_length.max(1))
+                .map(|n| n.id)
+                .collect();
+
+            while let Some(victim_id) = stealing.pick_victim(&mut victims) {
+                let thief = self.nodes.iter().find(|n| n.id == thief_id).unwrap();
+                let process_ids: Vec<u32> = thief.active_processes.iter().map(|p| p.id).collect();
+                let victim = self.nodes.iter().find(|n| n.id == victim_id).unwrap();
+                let position = victim.local_queue.iter().rposition(|p| {
+                    thief.can_host(&p.needed_resources) && is_allowed(&p.constraints, &thief.labels, &process_ids)
+                });
+                let position = match position {
+                    Some(position) => position,
+                    None => continue,
+                };
+                let victim = self.nodes.iter_mut().find(|n| n.id == victim_id).unwrap();
+                let process = victim.local_queue.remove(position).unwrap();
+
+                if !self.bind_process(thief_id, &process) {
+                    let victim = self.nodes.iter_mut().find(|n| n.id == victim_id).unwrap();
+                    victim.local_queue.insert(position, process);
+                    continue;
+                }
+                println!("Node {} stole process {} from node {}", thief_id, process.id, victim_id);
+                steals.push(Steal { thief_id, victim_id, process_id: process.id, at: now });
+                break;
+            }
+        }
+
+        stealing.steals.extend(steals.iter().cloned());
+        self.work_stealing = Some(stealing);
+        steals
+    }
+
+    // Propose moving processes off the busiest nodes when the rebalancer is due.
+    // Each move is a `RedistributeProcess` vote to the planned target, proposed by the node running the process.
+    // Returns the processes that were moved.
+    pub fn rebalance(&mut self) -> Vec<u32> {
+        let now = self.clock.now();
+        let mut rebalancer = match self.rebalancer.take() {
+            Some(rebalancer) if rebalancer.is_due(now) => rebalancer,
+            other => {
+                self.rebalancer = other;
+                return Vec::new();
+            }
+        };
+        rebalancer.last_run = Some(now);
+
+        let schedulable: Vec<u32> = self.cluster_snapshot().nodes.iter().map(|n| n.id).collect();
+        let nodes: Vec<&Node> = self.nodes.iter().filter(|n| schedulable.contains(&n.id)).collect();
+        let plan = rebalancer.plan(&nodes, now);
+        if !plan.is_empty() {
+            println!("Rebalancing: imbalance {:.2}, moves (process, target) {:?}", imbalance(&nodes), plan);
+        }
+
+        let mut moved = Vec::new();
+        for (process_id, target_node_id) in plan {
+            let host = |session: &Session| {
+                session.nodes.iter().find(|n| n.active_processes.iter().any(|p| p.id == process_id)).map(|n| n.id)
+            };
+            let from_node_id = match host(self) {
+                Some(node_id) => node_id,
+                None => continue,
+            };
+            rebalancer.last_moved.insert(process_id, now);
+            let action = Action::RedistributeProcess { process_id, target_node_id: Some(target_node_id) };
+            self.initiate_voting(from_node_id, action);
+            if host(self) == Some(target_node_id) {
+                moved.push(process_id);
+            }
+        }
+
+        self.rebalancer = Some(rebalancer);
+        moved
+    }
+
+    // Autoscaling
+
+    // Add a node from the provider when pending work or utilization has exceeded capacity for long enough,
+    // or drain the longest idle node it added when demand dropped. One decision per cooldown, within the node bounds.
+    // Processes waiting only for quota or for nodes matching their constraints aren't demand a new node would meet.
+    pub fn autoscale(&mut self) -> Vec<ScalingEvent> {
+        let mut autoscaler = match self.autoscaler.take() {
+            Some(autoscaler) => autoscaler,
+            None => return Vec::new(),
+        };
+        let now = self.clock.now();
+        let mut events = Vec::new();
+
+        // Nodes drained by the autoscaler that have left the cluster go back to the provider
+        let node_ids: Vec<u32> = self.nodes.iter().map(|n| n.id).collect();
+        for node_id in autoscaler.scaling_down.clone() {
+            if !node_ids.contains(&node_id) {
+                autoscaler.provider.release(node_id);
+                autoscaler.scaling_down.retain(|&id| id != node_id);
+                autoscaler.provisioned.retain(|&id| id != node_id);
+                events.push(ScalingEvent::Released { node_id, at: now });
+            }
+        }
+
+        let utilization = node_load(&self.total_resources, &self.available_resources);
+        let cluster = self.cluster_snapshot();
+        let waiting_for_capacity = self.processes.iter().any(|p| {
+            !matches!(cluster.rejection(p), Rejection::QuotaExceeded { .. } | Rejection::ConstraintsUnsatisfied { .. })
+        });
+        let pending = waiting_for_capacity || !self.jobs.is_empty();
+        let under_pressure = pending || utilization >= autoscaler.config.scale_up_utilization;
+        let scale_up = autoscaler.observe_pressure(under_pressure, now);
+
+        let idle_nodes: Vec<u32> = if !pending && utilization < autoscaler.config.scale_down_utilization {
+            self.nodes
+                .iter()
+                .filter(|n| autoscaler.provisioned.contains(&n.id))
+                .filter(|n| n.status == NodeStatus::Active && n.active_processes.is_empty() && n.local_queue.is_empty())
+                .map(|n| n.id)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let scale_down = autoscaler.observe_idle(&idle_nodes, now);
+
+        // Draining nodes are on their way out
+        let size = self.nodes.iter().filter(|n| n.status != NodeStatus::Draining).count();
+        if !autoscaler.in_cooldown(now) {
+            if scale_up && size < autoscaler.config.max_nodes {
+                let node_id = self.next_node_id();
+                match autoscaler.provider.provision(node_id) {
+                    Some(node) => {
+                        println!("Autoscaler: adding node {} (utilization {:.2}, pending work: {})", node_id, utilization, pending);
+                        self.add_node(node);
+                        autoscaler.provisioned.push(node_id);
+                        autoscaler.last_scaled = Some(now);
+                        autoscaler.pressure_since = None;
+                        events.push(ScalingEvent::ScaledUp { node_id, at: now });
+                    }
+                    None => eprintln!("Autoscaler: the provider has no node available"),
+                }
+            } else if let Some(node_id) = scale_down.filter(|_| size > autoscaler.config.min_nodes) {
+                // Proposed by another node, the drained node can't vote itself out
+                let proposer_id = self.nodes.iter().find(|n| n.id != node_id && n.status == NodeStatus::Active).map(|n| n.id);
+                if let Some(proposer_id) = proposer_id {
+                    println!("Autoscaler: draining idle node {} (utilization {:.2})", node_id, utilization);
+                    self.initiate_voting(proposer_id, Action::DrainNode { node_id, timeout: autoscaler.config.drain_timeout });
+                    if self.drains.contains_key(&node_id) {
+                        autoscaler.last_scaled = Some(now);
+                        autoscaler.idle_since.remove(&node_id);
+                        autoscaler.scaling_down.push(node_id);
+                        events.push(ScalingEvent::ScalingDown { node_id, at: now });
+                    }
+                }
+            }
+        }
+
+        autoscaler.events.extend(events.iter().cloned());
+        self.autoscaler = Some(autoscaler);
+        events
+    }
+
+    // Deadlines
+
+    // Queue a process, refusing it if it can't meet its deadline with the current load
+    pub fn submit_process(&mut self, process: Process) -> Result<(), AdmissionError> {
+        if let Some(deadline) = process.deadline {
+            let estimated_completion = self.estimated_completion(&process);
+            if estimated_completion > deadline {
+                let error = AdmissionError::DeadlineUnreachable { process_id: process.id, deadline, estimated_completion };
+                eprintln!("Process {} not admitted: {}", process.id, error);
+                return Err(error);
+            }
+        }
+        self.processes.push(process);
+        self.sort_pending();
+        Ok(())
+    }
+
+    // Estimated time a process would finish if queued now: the work queued ahead of it is spread
+    // over the schedulable nodes, and if no node has room it also waits for the first running process to end.
+    pub fn estimated_completion(&self, process: &Process) -> u64 {
+        let now = self.clock.now();
+        let cluster = self.cluster_snapshot();
+        let nodes = cluster.nodes.len().max(1) as u64;
+
+        let ahead: u64 = self
+            .processes
+            .iter()
+            .filter(|p| self.queue_order.compare(p, process) != Ordering::Greater)
+            .map(|p| remaining_time(p, now))
+            .sum();
+        let mut start = now + ahead / nodes;
+
+        if cluster.candidates(process).next().is_none() {
+            let wait = self
+                .nodes
+                .iter()
+                .filter(|n| cluster.nodes.iter().any(|c| c.id == n.id))
+                .filter(|n| n.total_resources.can_allocate(&process.needed_resources))
+                .flat_map(|n| n.active_processes.iter())
+                .map(|p| remaining_time(p, now))
+                .min()
+                .unwrap_or(0);
+            start += wait;
+        }
+        start + process.estimated_duration.unwrap_or(0)
+    }
+
+    // Record the pending or running processes whose deadline has passed, returns the new ones
+    pub fn check_deadlines(&mut self) -> Vec<u32> {
+        let now = self.clock.now();
+        let overdue: Vec<(u32, u64)> = self
+            .processes
+            .iter()
+            .chain(self.nodes.iter().flat_map(|n| n.active_processes.iter()))
+            .filter_map(|p| p.deadline.filter(|&deadline| deadline < now).map(|deadline| (p.id, deadline)))
+            .filter(|(id, _)| !self.missed_deadlines.iter().any(|m| m.process_id == *id))
+            .collect();
+
+        for (process_id, deadline) in overdue.iter() {
+            eprintln!("Process {} missed its deadline {} (now {})", process_id, deadline, now);
+            self.missed_deadlines.push(DeadlineMiss { process_id: *process_id, deadline: *deadline, detected_at: now });
+        }
+        overdue.into_iter().map(|(id, _)| id).collect()
+    }
+
+    // Place a process on a node and allocate its resources
+    fn place_process(&mut self, process: &Process) -> bool {
+        // Step 1: Let the scheduler choose a node with room for the process
+        let node_id = match self.select_node(process, None) {
+            Ok(id) => id,
+            Err(rejection) => {
+                eprintln!("Failed to assign process {}: {}", process.id, rejection);
+                return self.preempt_for(process);
+            }
+        };
+
+        self.bind_process(node_id, process)
+    }
+
+    // Evict lower priority processes from one node so the process fits there.
+    // The node needing the fewest evictions is chosen; the evicted processes are requeued.
+    fn preempt_for(&mut self, process: &Process) -> bool {
+        if !self.preemption || !within_quota(&self.quotas, &self.tenant_usage(), process) {
+            return false;
+        }
+        let plan = self
+            .cluster_snapshot()
+            .nodes
+            .iter()
+            .filter(|snapshot| snapshot.is_allowed(process))
+            .filter_map(|snapshot| {
+                let node = self.nodes.iter().find(|n| n.id == snapshot.id)?;
+                Some((node.id, preemption_victims(node, process)?))
+            })
+            .min_by_key(|(_, victims)| victims.len());
+        let (node_id, victims) = match plan {
+            Some(plan) => plan,
+            None => return false,
+        };
+
+        for victim in victims {
+            println!(
+                "Preempting process {} ({:?}) on node {} for process {} ({:?})",
+                victim.id, victim.priority, node_id, process.id, process.priority
+            );
+            self.requeue_running(node_id, victim.id);
+        }
+        self.sort_pending();
+        self.bind_process(node_id, process)
+    }
+
+    // Place every process of a job or none of them
+    fn place_job(&mut self, job: &Job) -> bool {
+        let mut cluster = self.cluster_snapshot();
+        let placements: Result<Vec<u32>, Rejection> =
+            self.scheduler.place_batch(&job.processes, &mut cluster).into_iter().collect();
+        let node_ids = match placements {
+            Ok(node_ids) => node_ids,
+            Err(rejection) => {
+                eprintln!("Job {} can't be placed as a whole: {}", job.id, rejection);
+                return false;
+            }
+        };
+
+        // Reserve on every node, rolling back if any reservation fails
+        let mut bound: Vec<(u32, &Process)> = Vec::new();
+        for (process, node_id) in job.processes.iter().zip(node_ids) {
+            if !self.bind_process(node_id, process) {
+                eprintln!("Job {}: reservation on node {} failed, rolling back", job.id, node_id);
+                for (node_id, process) in bound {
+                    self.unbind_process(node_id, process);
+                }
+                return false;
+            }
+            bound.push((node_id, process));
+        }
+        println!("Job {} placed with all its {} processes", job.id, job.processes.len());
+        true
+    }
+
+    // Undo `bind_process`
+    fn unbind_process(&mut self, node_id: u32, process: &Process) {
+        if let Some(node) = self.nodes.iter_mut().find(|node| node.id == node_id) {
+            node.active_processes.retain(|p| p.id != process.id);
+        }
+        self.deallocate_resources(node_id, &process.needed_resources);
+    }
+
+    // Allocate the process' resources on the node if its tenant's quota allows it
+    fn reserve_process(&mut self, node_id: u32, process: &Process) -> bool {
+        if !within_quota(&self.quotas, &self.tenant_usage(), process) {
+            eprintln!("Process {} exceeds the quota of tenant '{}'", process.id, tenant_of(process));
+            return false;
+        }
+        self.allocate_resources(node_id, &process.needed_resources)
+    }
+
+    // Allocate the process' resources on the node and assign it there
+    fn bind_process(&mut self, node_id: u32, process: &Process) -> bool {
+        if !self.reserve_process(node_id, process) {
+            return false;
+        }
+
+        // Scheduled once the node is chosen, processes from a local queue already are
+        let now = self.clock.now();
+        let mut process = process.clone();
+        let scheduled = match process.state {
+            ProcessState::Scheduled => Ok(()),
+            _ => process.transition(ProcessState::Scheduled, now),
+        };
+        let started = match self.nodes.iter_mut().find(|node| node.id == node_id) {
+            Some(node) => scheduled.and_then(|_| node.start_process(process.clone(), now)),
+            None => return false,
+        };
+        if let Err(error) = started {
+            eprintln!("{}, process {} not assigned to node {}", error, process.id, node_id);
+            self.deallocate_resources(node_id, &process.needed_resources);
+            return false;
+        }
+        println!("Assigned process {} to node {}", process.id, node_id);
+        true
+    }
+
+    // Put a process back in the cluster queue, it starts over when placed again
+    fn requeue(&mut self, mut process: Process) {
+        if process.state != ProcessState::Pending {
+            if let Err(error) = process.transition(ProcessState::Pending, self.clock.now()) {
+                eprintln!("{}, not requeued", error);
+                return;
+            }
+        }
+        self.processes.push(Process { paused: false, started_at: None, ..process });
+        self.sort_pending();
+    }
+
+    // Take a running process off its node, releasing its resources, and requeue it
+    fn requeue_running(&mut self, node_id: u32, process_id: u32) {
+        let now = self.clock.now();
+        let stopped = match self.nodes.iter_mut().find(|n| n.id == node_id) {
+            Some(node) => node.stop_process(process_id, ProcessState::Pending, now),
+            None => return,
+        };
+        match stopped {
+            Ok(Some(process)) => {
+                self.kill_command(process_id);
+                self.deallocate_resources(node_id, &process.needed_resources);
+                self.requeue(process);
+            }
+            Ok(None) => {}
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+
+    // Reschedule the processes of a failed node, applying the orphan policy when capacity is short
+    fn reschedule_orphans(&mut self, mut orphans: Vec<Process>) {
+        // The most important processes take the remaining capacity first
+        orphans.sort_by_key(|p| std::cmp::Reverse(p.priority));
+        let now = self.clock.now();
+        for mut process in orphans {
+            self.kill_command(process.id);
+            if process.state != ProcessState::Pending {
+                if let Err(error) = process.transition(ProcessState::Pending, now) {
+                    eprintln!("{}, process {} not rescheduled", error, process.id);
+                    continue;
+                }
+            }
+            if self.place_process(&process) {
+                continue;
+            }
+
+            match self.orphan_policy.clone() {
+                OrphanPolicy::Queue => {
+                    println!("Process {} queued until capacity frees up", process.id);
+                    self.processes.push(process);
+                    self.sort_pending();
+                }
+                OrphanPolicy::ShedLowestPriority => {
+                    println!("Process {} shed for lack of capacity", process.id);
+                    let process_id = process.id;
+                    if let Err(error) = process.transition(ProcessState::Failed, now) {
+                        eprintln!("{}, process {} not shed", error, process_id);
+                        continue;
+                    }
+                    self.shed_processes.push(process);
+                    self.finish_workflow_task(process_id, Some("Shed for lack of capacity".to_string()));
+                }
+                OrphanPolicy::ScaleOut { node_resources } => {
+                    // A node too small for the process would only add idle capacity
+                    if node_resources.can_allocate(&process.needed_resources) {
+                        let new_node_id = self.next_node_id();
+                        println!("Scaling out with node {} to host process {}", new_node_id, process.id);
+                        self.add_node(Node::with_resources(new_node_id, node_resources));
+                    }
+
+                    if !self.place_process(&process) {
+                        println!("Process {} queued, new nodes are too small for it", process.id);
+                        self.processes.push(process);
+                        self.sort_pending();
+                    }
+                }
+            }
+        }
+    }
+
+    // Id for a node created by the session. Don't reuse the id of a node being recovered.
+    fn next_node_id(&self) -> u32 {
+        self.nodes
+            .iter()
+            .map(|n| n.id)
+            .chain(self.recoveries.keys().copied())
+            .max()
+            .unwrap_or(0)
+            + 1
+    }
+
+    // Resources used by the running processes of every tenant
+    pub fn tenant_usage(&self) -> HashMap<String, Resources> {
+        tenant_usage(self.nodes.iter().flat_map(|n| n.active_processes.iter()))
+    }
+
+    // Nodes that can receive processes. Nodes in a minority partition stop scheduling.
+    pub fn cluster_snapshot(&self) -> ClusterSnapshot {
+        let mut nodes: Vec<NodeSnapshot> = self.nodes.iter()
+            .filter(|node| node.status == NodeStatus::Active && self.has_quorum(node.id))
+            .map(|node| NodeSnapshot {
+                id: node.id,
+                total_resources: node.total_resources.clone(),
+                available_resources: node.available_resources.clone(),
+                labels: node.labels.clone(),
+                process_ids: node.active_processes.iter().map(|p| p.id).collect(),
+            })
+            .collect();
+        nodes.sort_by_key(|node| node.id);
+        ClusterSnapshot {
+            nodes,
+            tenant_usage: self.tenant_usage(),
+            quotas: self.quotas.clone(),
+        }
+    }
+
+    // Node chosen by the scheduler for a process, optionally excluding one
+    fn select_node(&mut self, process: &Process, exclude: Option<u32>) -> Result<u32, Rejection> {
+        let mut cluster = self.cluster_snapshot();
+        cluster.nodes.retain(|node| Some(node.id) != exclude);
+        self.scheduler.place(process, &cluster)
+    }
+
+    // Move a process from its current node to the target, or to the node chosen by the scheduler without one
+    pub fn redistribute_process(&mut self, process_id: u32, target_node_id: Option<u32>) -> ActionOutcome {
+        let current_node_id = self
+            .nodes
+            .iter()
+            .find(|n| n.active_processes.iter().any(|p| p.id == process_id))
+            .map(|n| n.id);
+
+        let process = match current_node_id {
+            Some(node_id) => self
+                .nodes
+                .iter()
+                .find(|n| n.id == node_id)
+                .and_then(|n| n.active_processes.iter().find(|p| p.id == process_id))
+                .cloned(),
+            None => self.processes.iter().find(|p| p.id == process_id).cloned(),
+        };
+        let process = match process {
+            Some(process) => process,
+            None => {
+                eprintln!("Process {} not found, nothing to redistribute", process_id);
+                return ActionOutcome::ProcessNotFound { process_id };
+            }
+        };
+
+        let target_node_id = match target_node_id {
+            // The proposer chose the node, it must still be able to host the process
+            Some(node_id) => {
+                let fits = self.cluster_snapshot().nodes.iter().any(|node| {
+                    node.id == node_id && Some(node_id) != current_node_id && node.can_host(&process) && node.is_allowed(&process)
+                });
+                if !fits {
+                    eprintln!("Node {} can't host process {}, it stays in place", node_id, process_id);
+                    return ActionOutcome::NoCapacity { process_id };
+                }
+                node_id
+            }
+            None => match self.select_node(&process, current_node_id) {
+                Ok(id) => id,
+                Err(rejection) => {
+                    eprintln!("{}, process {} stays in place", rejection, process_id);
+                    return ActionOutcome::NoCapacity { process_id };
+                }
+            },
+        };
+
+        match current_node_id {
+            // The process keeps running, only its reservation and its command move with it
+            Some(node_id) => {
+                self.unbind_process(node_id, &process);
+                let moved = self.reserve_process(target_node_id, &process);
+                let host_id = if moved { target_node_id } else { node_id };
+                if !moved {
+                    // Back where it was, the reservation it just released is still free
+                    self.allocate_resources(node_id, &process.needed_resources);
+                }
+                if let Some(node) = self.nodes.iter_mut().find(|n| n.id == host_id) {
+                    node.active_processes.push(process);
+                }
+                if !moved {
+                    return ActionOutcome::NoCapacity { process_id };
+                }
+                self.move_command(process_id, target_node_id);
+            }
+            None => {
+                self.processes.retain(|p| p.id != process_id);
+                if !self.bind_process(target_node_id, &process) {
+                    self.processes.push(process);
+                    self.sort_pending();
+                    return ActionOutcome::NoCapacity { process_id };
+                }
+            }
+        }
+        println!(
+            "Process {} moved from {:?} to node {}",
+            process_id, current_node_id, target_node_id
+        );
+        ActionOutcome::Redistributed {
+            process_id,
+            from_node_id: current_node_id,
+            to_node_id: target_node_id,
+        }
+    }
+
+    // Live migration
+
+    // Start moving a running process to another node without restarting it
+    pub fn start_migration(&mut self, proposer_id: u32, process_id: u32, target_node_id: u32) {
+        let source = self
+            .nodes
+            .iter()
+            .find_map(|n| n.active_processes.iter().find(|p| p.id == process_id).map(|p| (n.id, p.clone())));
+
+        let mut migration = match &source {
+            Some((source_node_id, process)) => {
+                let (source_node_id, pages) = (*source_node_id, process.state_pages());
+                Migration::new(process_id, source_node_id, target_node_id, proposer_id, pages)
+            }
+            None => Migration::new(process_id, 0, target_node_id, proposer_id, 0),
+        };
+
+        if source.is_none() {
+            migration.abort(format!("Process {} is not running", process_id));
+        } else if migration.source_node_id == target_node_id {
+            migration.abort(format!("Process {} already runs on node {}", process_id, target_node_id));
+        } else if self.migrations.iter().any(|m| m.process_id == process_id && !m.is_finished()) {
+            migration.abort(format!("Process {} is already being migrated", process_id));
+        } else if let Some((_, process)) = &source {
+            // The target must be one the scheduler could pick for the process
+            let fits = self.cluster_snapshot().nodes.iter().any(|node| {
+                node.id == target_node_id && node.can_host(process) && node.is_allowed(process)
+            });
+            // Room on the target is reserved up front so the cutover can't fail for lack of it
+            if fits && self.allocate_resources(target_node_id, &process.needed_resources) {
+                migration.reserved = process.needed_resources.clone();
+            } else {
+                migration.abort(format!("Node {} can't host process {}", target_node_id, process_id));
+            }
+        }
+
+        println!(
+            "Starting migration of process {} from node {} to node {}",
+            process_id, migration.source_node_id, target_node_id
+        );
+        if migration.is_finished() {
+            self.report_migration(&migration);
+        }
+        self.migrations.push(migration);
+    }
+
+    // Advance every ongoing migration by one step
+    pub fn advance_migrations(&mut self) {
+        let mut migrations = std::mem::take(&mut self.migrations);
+
+        for migration in migrations.iter_mut().filter(|m| !m.is_finished()) {
+            self.step_migration(migration);
+            if migration.is_finished() {
+                self.report_migration(migration);
+            }
+        }
+
+        self.migrations = migrations;
+    }
+
+    fn step_migration(&mut self, migration: &mut Migration) {
+        let process_id = migration.process_id;
+        let source_id = migration.source_node_id;
+        let target_id = migration.target_node_id;
+
+        // Past the cutover the process only runs on the target, the source just releases its resources
+        if migration.phase == MigrationPhase::Cleanup {
+            self.deallocate_resources(source_id, &migration.reserved);
+            migration.phase = MigrationPhase::Completed;
+            println!("Migration of process {} to node {} completed", process_id, target_id);
+            return;
+        }
+
+        // Abort if the process or any of the nodes went away
+        let source_has_process = self
+            .nodes
+            .iter()
+            .any(|n| n.id == source_id && n.active_processes.iter().any(|p| p.id == process_id));
+        let target_ready = self
+            .nodes
+            .iter()
+            .any(|n| n.id == target_id && n.status == NodeStatus::Active)
+            && self.can_communicate(source_id, target_id);
+        if !source_has_process || !target_ready {
+            let reason = if !source_has_process {
+                format!("Process {} no longer runs on node {}", process_id, source_id)
+            } else {
+                format!("Target node {} is not available", target_id)
+            };
+            self.set_process_paused(source_id, process_id, false);
+            self.deallocate_resources(target_id, &migration.reserved);
+            migration.abort(reason);
+            return;
+        }
+
+        match migration.phase {
+            MigrationPhase::PreCopy => {
+                if migration.precopy_round(&self.migration_config) {
+                    println!("Pausing process {} on node {} for cutover", process_id, source_id);
+                    self.set_process_paused(source_id, process_id, true);
+                    migration.phase = MigrationPhase::Paused;
+                }
+            }
+            MigrationPhase::Paused => {
+                migration.stop_and_copy();
+                migration.phase = MigrationPhase::CutOver;
+            }
+            MigrationPhase::CutOver => {
+                // The process leaves the source and resumes on the target in one step,
+                // so it never runs on both nodes
+                let process = self.nodes.iter_mut().find(|n| n.id == source_id).and_then(|source| {
+                    let index = source.active_processes.iter().position(|p| p.id == process_id)?;
+                    Some(source.active_processes.remove(index))
+                });
+                if let (Some(mut process), Some(target)) =
+                    (process, self.nodes.iter_mut().find(|n| n.id == target_id))
+                {
+                    process.paused = false;
+                    target.active_processes.push(process);
+                    println!("Process {} resumed on node {}", process_id, target_id);
+                }
+                self.move_command(process_id, target_id);
+                migration.phase = MigrationPhase::Cleanup;
+            }
+            MigrationPhase::Cleanup | MigrationPhase::Completed | MigrationPhase::Aborted { .. } => {}
+        }
+    }
+
+    fn set_process_paused(&mut self, node_id: u32, process_id: u32, paused: bool) {
+        if let Some(process) = self
+            .nodes
+            .iter_mut()
+            .filter(|n| n.id == node_id)
+            .flat_map(|n| n.active_processes.iter_mut())
+            .find(|p| p.id == process_id)
+        {
+            process.paused = paused;
+        }
+    }
+
+    fn report_migration(&mut self, migration: &Migration) {
+        let outcome = match &migration.phase {
+            MigrationPhase::Aborted { reason } => {
+                eprintln!("Migration of process {} aborted: {}", migration.process_id, reason);
+                ActionOutcome::MigrationAborted { process_id: migration.process_id, reason: reason.clone() }
+            }
+            _ => ActionOutcome::Migrated {
+                process_id: migration.process_id,
+                from_node_id: migration.source_node_id,
+                to_node_id: migration.target_node_id,
+            },
+        };
+        let action = Action::MigrateProcess {
+            process_id: migration.process_id,
+            target_node_id: migration.target_node_id,
+        };
+        if let Some(proposer) = self.nodes.iter_mut().find(|n| n.id == migration.proposer_id) {
+            proposer.receive_outcome(action, outcome);
+        }
+    }
+
+    // Graceful drain
+
+    // Make a node unschedulable and start migrating its processes away
+    pub fn start_drain(&mut self, proposer_id: u32, node_id: u32, timeout: u64) {
+        let now = self.clock.now();
+        let (process_ids, queued): (Vec<u32>, Vec<Process>) = match self.nodes.iter_mut().find(|n| n.id == node_id) {
+            Some(node) => {
+                node.status = NodeStatus::Draining;
+                (node.active_processes.iter().map(|p| p.id).collect(), node.local_queue.drain(..).collect())
+            }
+            None => {
+                let action = Action::DrainNode { node_id, timeout };
+                if let Some(proposer) = self.nodes.iter_mut().find(|n| n.id == proposer_id) {
+                    proposer.receive_outcome(action, ActionOutcome::NodeNotFound { node_id });
+                }
+                return;
+            }
+        };
+        println!("Draining node {} until {}", node_id, now + timeout);
+
+        // Processes that haven't started yet go back to the cluster queue, nobody steals from a draining node
+        for process in queued {
+            self.requeue(process);
+        }
+
+        let mut drain = DrainState::new(node_id, proposer_id, now, timeout);
+        for process_id in process_ids {
+            // Processes without a target keep running until they finish or the deadline passes
+            let process = self
+                .nodes
+                .iter()
+                .find(|n| n.id == node_id)
+                .and_then(|n| n.active_processes.iter().find(|p| p.id == process_id))
+                .cloned();
+            if let Some(target_node_id) = process.and_then(|p| self.select_node(&p, Some(node_id)).ok()) {
+                // The drained node is about to leave, the outcomes go to the proposer of the drain
+                self.start_migration(proposer_id, process_id, target_node_id);
+                drain.migrating.push(process_id);
+            }
+        }
+        self.drains.insert(node_id, drain);
+    }
+
+    // Remove the drained nodes that are empty or whose deadline has passed
+    pub fn advance_drains(&mut self) {
+        let now = self.clock.now();
+        let mut node_ids: Vec<u32> = self.drains.keys().copied().collect();
+        node_ids.sort();
+
+        for node_id in node_ids {
+            let remaining: Vec<Process> = self
+                .nodes
+                .iter()
+                .find(|n| n.id == node_id)
+                .map(|n| n.active_processes.clone())
+                .unwrap_or_default();
+            if !remaining.is_empty() && !self.drains[&node_id].is_expired(now) {
+                continue;
+            }
+            if let Some(drain) = self.drains.remove(&node_id) {
+                self.finish_drain(drain, remaining);
+            }
+        }
+    }
+
+    fn finish_drain(&mut self, drain: DrainState, remaining: Vec<Process>) {
+        let node_id = drain.node_id;
+        let running_elsewhere = |session: &Session, process_id: u32| {
+            session
+                .nodes
+                .iter()
+                .any(|n| n.id != node_id && n.active_processes.iter().any(|p| p.id == process_id))
+        };
+
+        // Processes still on the node are requeued, unless a migration already resumed them elsewhere
+        let mut orphans = Vec::new();
+        for process in remaining {
+            if !running_elsewhere(self, process.id) {
+                self.deallocate_resources(node_id, &process.needed_resources);
+                orphans.push(process);
+            }
+        }
+        let requeued: Vec<u32> = orphans.iter().map(|p| p.id).collect();
+        let migrated: Vec<u32> = drain
+            .migrating
+            .iter()
+            .copied()
+            .filter(|&id| running_elsewhere(self, id))
+            .collect();
+
+        // Release the node's locks before it leaves
+        self.release_resource(node_id);
+        self.remove_node(node_id);
+        println!("Node {} drained and removed", node_id);
+
+        self.reschedule_orphans(orphans);
+
+        let action = Action::DrainNode { node_id, timeout: drain.timeout };
+        let outcome = ActionOutcome::Drained { node_id, migrated, requeued };
+        if let Some(proposer) = self.nodes.iter_mut().find(|n| n.id == drain.proposer_id) {
+            proposer.receive_outcome(action, outcome);
+        }
+    }
+
+    // Process completion
+
+    // A running process finished its work: release its resources
+    pub fn complete_process(&mut self, process_id: u32) -> bool {
+        // A process finishing after its deadline is reported too
+        self.check_deadlines();
+        let now = self.clock.now();
+        let finished = self.nodes.iter_mut().find_map(|node| {
+            let stopped = node.stop_process(process_id, ProcessState::Succeeded, now).transpose()?;
+            Some((node.id, stopped))
+        });
+        match finished {
+            Some((node_id, Ok(process))) => {
+                println!("Process {} completed on node {}", process_id, node_id);
+                self.kill_command(process_id);
+                self.deallocate_resources(node_id, &process.needed_resources);
+                self.finished_processes.push(process);
+                self.finish_workflow_task(process_id, None);
+                true
+            }
+            Some((_, Err(error))) => {
+                eprintln!("{}", error);
+                false
+            }
+            None => false,
+        }
+    }
+
+    // A running process failed: release its resources
+    pub fn fail_process(&mut self, process_id: u32, reason: String) -> bool {
+        let now = self.clock.now();
+        let failed = self.nodes.iter_mut().find_map(|node| {
+            let mut process = node.active_processes.iter().find(|p| p.id == process_id)?.clone();
+            if let Err(error) = process.transition(ProcessState::Failed, now) {
+                return Some((node.id, Err(error)));
+            }
+            node.handle_process_failure(process_id, reason.clone());
+            Some((node.id, Ok(process)))
+        });
+        match failed {
+            Some((node_id, Ok(process))) => {
+                self.kill_command(process_id);
+                self.deallocate_resources(node_id, &process.needed_resources);
+                self.finished_processes.push(process);
+                self.finish_workflow_task(process_id, Some(reason));
+                true
+            }
+            Some((_, Err(error))) => {
+                eprintln!("{}", error);
+                false
+            }
+            None => false,
+        }
+    }
+
+    // Record how the command of a running process ended: completed if it exited successfully,
+    // failed otherwise. Its resources are released and the output kept with the finished process.
+    pub fn record_exit(&mut self, process_id: u32, result: &io::Result<ProcessOutput>) -> bool {
+        let recorded = match result {
+            Ok(output) if output.success => self.complete_process(process_id),
+            Ok(output) => {
+                let reason = format!("Exited with code {:?}: {}", output.exit_code, output.stderr.trim());
+                self.fail_process(process_id, reason)
+            }
+            Err(error) => self.fail_process(process_id, format!("Couldn't start: {}", error)),
+        };
+        if let (true, Ok(output)) = (recorded, result) {
+            if let Some(process) = self.finished_processes.iter_mut().rev().find(|p| p.id == process_id) {
+                process.output = Some(output.clone());
+            }
+        }
+        recorded
+    }
+
+    // Stop a process wherever it is: in the cluster queue, in a node's local queue or running
+    pub fn cancel_process(&mut self, process_id: u32) -> bool {
+        let now = self.clock.now();
+        let running = self.nodes.iter_mut().find_map(|node| {
+            let process = node.stop_process(process_id, ProcessState::Cancelled, now).ok().flatten()?;
+            Some((node.id, process))
+        });
+        let queued = match self.processes.iter().position(|p| p.id == process_id) {
+            Some(index) => Some(self.processes.remove(index)),
+            None => self.nodes.iter_mut().find_map(|node| {
+                let index = node.local_queue.iter().position(|p| p.id == process_id)?;
+                node.local_queue.remove(index)
+            }),
+        };
+
+        let process = match (running, queued) {
+            (Some((node_id, process)), _) => {
+                println!("Process {} cancelled on node {}", process_id, node_id);
+                self.kill_command(process_id);
+                self.deallocate_resources(node_id, &process.needed_resources);
+                process
+            }
+            (None, Some(mut process)) => {
+                println!("Process {} cancelled before starting", process_id);
+                let _ = process.transition(ProcessState::Cancelled, now);
+                process
+            }
+            (None, None) => return false,
+        };
+        self.finished_processes.push(process);
+        self.finish_workflow_task(process_id, Some("Cancelled".to_string()));
+        true
+    }
+
+    // The process now runs on another node, its command keeps running
+    fn move_command(&mut self, process_id: u32, node_id: u32) {
+        if let Some(running) = self.running_commands.get_mut(&process_id) {
+            running.node_id = node_id;
+        }
+    }
+
+    // Kill the OS command of a process that stopped running, if any
+    fn kill_command(&mut self, process_id: u32) {
+        if let Some(running) = self.running_commands.remove(&process_id) {
+            println!("Killing the command of process {} on node {}", process_id, running.node_id);
+            let _ = running.kill.send(());
+        }
+    }
+
+    // State of a known process
+    pub fn process_state(&self, process_id: u32) -> Option<ProcessState> {
+        self.all_processes().find(|p| p.id == process_id).map(|p| p.state)
+    }
+
+    // Every known process in a given state
+    pub fn processes_in_state(&self, state: ProcessState) -> Vec<&Process> {
+        self.all_processes().filter(|p| p.state == state).collect()
+    }
+
+    // Queued, running and finished processes
+    fn all_processes(&self) -> impl Iterator<Item = &Process> {
+        self.processes
+            .iter()
+            .chain(self.jobs.iter().flat_map(|job| job.processes.iter()))
+            .chain(self.nodes.iter().flat_map(|n| n.local_queue.iter().chain(n.active_processes.iter())))
+            .chain(self.finished_processes.iter())
+            .chain(self.shed_processes.iter())
+    }
+
+    // Workflows
+
+    pub fn submit_workflow(&mut self, workflow: Workflow) {
+        println!("Workflow {} submitted with {} tasks", workflow.id, workflow.tasks.len());
+        self.workflows.push(workflow);
+        self.advance_workflows();
+    }
+
+    // Queue a process for every task whose dependencies have succeeded
+    pub fn advance_workflows(&mut self) {
+        let mut workflows = std::mem::take(&mut self.workflows);
+        for workflow in workflows.iter_mut() {
+            let ready: Vec<Task> = workflow.ready_tasks().into_iter().cloned().collect();
+            for task in ready {
+                let process_id = self.next_process_id();
+                println!(
+                    "Workflow {}: task {} is ready, queued as process {}",
+                    workflow.id, task.id, process_id
+                );
+                workflow.start(task.id, process_id);
+                self.processes.push(Process::new(process_id, task.description, task.resource_requirements));
+            }
+        }
+        self.workflows = workflows;
+        self.sort_pending();
+    }
+
+    pub fn workflow_report(&self, workflow_id: u32) -> Option<WorkflowReport> {
+        self.workflows.iter().find(|w| w.id == workflow_id).map(|w| w.report())
+    }
+
+    // Record the result of a process running a workflow task, None when it succeeded
+    fn finish_workflow_task(&mut self, process_id: u32, failure: Option<String>) {
+        for workflow in self.workflows.iter_mut() {
+            let task_id = match workflow.task_for_process(process_id) {
+                Some(task_id) => task_id,
+                None => continue,
+            };
+            match failure.clone() {
+                None => workflow.complete(task_id),
+                Some(reason) => {
+                    if workflow.fail(task_id, reason) {
+                        println!("Workflow {}: retrying task {}", workflow.id, task_id);
+                    }
+                }
+            }
+            if workflow.status() != WorkflowStatus::Running {
+                println!("Workflow {} finished: {:?}", workflow.id, workflow.report());
+            }
+        }
+        self.advance_workflows();
+    }
+
+    // Id not used by any known process nor by any process created before
+    fn next_process_id(&mut self) -> u32 {
+        let highest = self.all_processes().map(|p| p.id).max().unwrap_or(0);
+        self.last_process_id = self.last_process_id.max(highest) + 1;
+        self.last_process_id
+    }
+
+    // Initiate voting on an action proposed by a node
+    pub fn initiate_voting(&mut self, proposer_id: u32, action: Action) {
+        println!(
+            "Session initiating voting on action {:?} proposed by Node {}",
+            action, proposer_id
+        );
+
+        // Split-brain protection: a minority partition can't take cluster decisions
+        if !self.has_quorum(proposer_id) {
+            println!(
+                "Node {} is in a minority partition, action {:?} rejected",
+                proposer_id, action
+            );
+            return;
+        }
+        // A node can't vote itself out of the cluster, another member has to propose its drain
+        if let Action::DrainNode { node_id, .. } = &action {
+            if *node_id == proposer_id {
+                println!("Node {} can't propose its own drain, action {:?} rejected", proposer_id, action);
+                return;
+            }
+        }
+
+        // Record that the proposer has voted for the action
+        self.pending_votes
+            .insert(action.clone(), (proposer_id, vec![(proposer_id, Vote::Approve)]));
+
+        // Collect votes from other nodes
+        let mut collected_votes = vec![];
+        let now = self.clock.now();
+
+        let network = &self.network;
+        for node in self
+            .nodes
+            .iter_mut()
+            .filter(|n| n.id != proposer_id && n.status != NodeStatus::Halted && network.can_reach(proposer_id, n.id))
+        {
+            // Let the failure detector account for the time elapsed since the last heartbeat
+            node.failure_detector.tick(now);
+            let vote = node.receive_proposal(action.clone());
+            collected_votes.push((node.id, vote));
+        }
+
+        // Votes travel back to the proposer
+        for (node_id, vote) in collected_votes {
+            self.send_message(node_id, proposer_id, Message::Vote { action: action.clone(), vote });
+        }
+
+        // Check if consensus is reached
+        self.check_consensus(action);
+    }
+
+    // Nodes call this method to cast their vote
+    pub fn cast_vote(&mut self, node_id: u32, action: Action, vote: Vote) {
+        let votes = match self.pending_votes.get_mut(&action) {
+            Some((_, votes)) => votes,
+            None => {
+                println!("Node {} voted for action {:?}, which is not being voted on", node_id, action);
+                return;
+            }
+        };
+        // A node only votes once per action
+        if votes.iter().any(|(id, _)| *id == node_id) {
+            return;
+        }
+        votes.push((node_id, vote.clone()));
+        println!("Node {} voted {:?} for action {:?}", node_id, vote, action);
+    }
+
+    // Check if the action has received enough votes
+    fn check_consensus(&mut self, action: Action) {
+        if let Some((proposer_id, votes)) = self.pending_votes.get(&action) {
+            let proposer_id = *proposer_id;
+            let total_nodes = self.nodes.len();
+
+            let approvals = votes
+                .iter()
+                .filter(|(_, v)| *v == Vote::Approve)
+                .count();
+
+            if approvals > total_nodes / 2 {
+                println!("Consensus reached on action {:?}", action);
+                self.execute_action(proposer_id, action.clone());
+                self.pending_votes.remove(&action);
+            } else if votes.len() - approvals > total_nodes / 2 {
+                println!("Consensus rejected on action {:?}", action);
+                self.pending_votes.remove(&action);
+                if let Action::AdmitNode { node_id } = action {
+                    self.pending_joins.remove(&node_id);
+                }
+            }
+            // Else, keep waiting for more votes
+        }
+    }
+
+    // Execute the action once consensus is reached
+    fn execute_action(&mut self, proposer_id: u32, action: Action) {
+        self.committed_actions.push(action.clone());
+        match action.clone() {
+            Action::ProcessFailure { node_id, reason } => {
+                println!("Executing ProcessFailure action for node {}: {}", node_id, reason);
+                self.handle_node_failure(node_id, reason);
+            },
+            Action::RedistributeProcess { process_id, target_node_id } => {
+                println!("Executing RedistributeProcess action for process {}", process_id);
+                let outcome = self.redistribute_process(process_id, target_node_id);
+                if let Some(proposer) = self.nodes.iter_mut().find(|n| n.id == proposer_id) {
+                    proposer.receive_outcome(action, outcome);
+                }
+            },
+            Action::NodeFailure { node_id, reason } => {
+                println!("Executing NodeFailure action for node {}: {}", node_id, reason);
+                self.handle_node_failure(node_id, reason);
+            },
+            Action::MigrateProcess { process_id, target_node_id } => {
+                println!("Executing MigrateProcess action for process {} to node {}", process_id, target_node_id);
+                self.start_migration(proposer_id, process_id, target_node_id);
+            },
+            Action::DrainNode { node_id, timeout } => {
+                println!("Executing DrainNode action for node {}", node_id);
+                self.start_drain(proposer_id, node_id, timeout);
+            },
+            Action::AdmitNode { node_id } => {
+                println!("Executing AdmitNode action for node {}", node_id);
+                self.admit_node(node_id);
+            },
+            // Handle other actions
+        }
+    }
+
+    pub fn handle_node_failure(&mut self, node_id: u32, reason: String) {
+        println!("Handling failure of node {}: {}", node_id, reason);
+
+        //Finds the node failure
+        if let Some(node_index) = self.nodes.iter().position(|n| n.id == node_id) {
+            // Extract active processes
+            let active_processes = self.nodes[node_index].active_processes.clone();
+        
+            // Deallocate resources
+            for process in &active_processes {
+                self.deallocate_resources(node_id, &process.needed_resources);
+            }
+            let capacity = self.nodes[node_index].total_resources.clone();
+        
+            // Remove the node
+            self.remove_node(node_id);
+
+            // Redistribute processes onto the surviving nodes
+            self.reschedule_orphans(active_processes.clone());
+            println!("Node {} deleted. Processes reassigned.", node_id);
+        
+            // Try to reinstall the node through the recovery workflow
+            let former_assignments = active_processes.iter().map(|p| p.id).collect();
+            let first_attempt_at = self.clock.now() + self.recovery_policy.initial_backoff;
+            self.recoveries
+                .insert(node_id, RecoveryState::new(node_id, former_assignments, capacity, first_attempt_at));
+            self.try_to_reinstall_node(node_id);
+        }
+        
+    }
+
+    // Messaging and fault injection
+
+    // Send a message through the simulated network, subject to the fault injector
+    pub fn send_message(&mut self, from_node_id: u32, to_node_id: u32, message: Message) {
+        let now = self.clock.now();
+        let envelope = Envelope { from_node_id, to_node_id, message, deliver_at: now };
+
+        let fault = match self.fault_injector.as_mut() {
+            Some(injector) => injector.message_fault(),
+            None => MessageFault::Deliver,
+        };
+
+        match fault {
+            MessageFault::Deliver => self.deliver(envelope),
+            MessageFault::Drop => {
+                println!("Message from node {} to node {} dropped", from_node_id, to_node_id);
+            }
+            MessageFault::Delay(delay) => {
+                println!("Message from node {} to node {} delayed {}ms", from_node_id, to_node_id, delay);
+                self.network.enqueue(Envelope { deliver_at: now + delay, ..envelope });
+            }
+            MessageFault::Duplicate => {
+                println!("Message from node {} to node {} duplicated", from_node_id, to_node_id);
+                self.deliver(envelope.clone());
+                self.deliver(envelope);
+            }
+            MessageFault::Reorder => {
+                // Arrives on the next delivery round, after messages sent later
+                self.network.enqueue(envelope);
+            }
+        }
+    }
+
+    // Deliver the delayed messages that are due
+    pub fn deliver_messages(&mut self) {
+        let now = self.clock.now();
+        for envelope in self.network.take_due(now) {
+            let action = match &envelope.message {
+                Message::Vote { action, .. } => Some(action.clone()),
+                Message::Reply => None,
+            };
+            self.deliver(envelope);
+            // A late vote may complete a pending decision
+            if let Some(action) = action {
+                self.check_consensus(action);
+            }
+        }
+    }
+
+    fn deliver(&mut self, envelope: Envelope) {
+        if !self.network.can_reach(envelope.from_node_id, envelope.to_node_id) {
+            println!(
+                "Message from node {} to node {} lost: node unreachable",
+                envelope.from_node_id, envelope.to_node_id
+            );
+            return;
+        }
+
+        match envelope.message {
+            Message::Vote { action, vote } => {
+                // Votes arriving after the decision are ignored
+                if self.pending_votes.contains_key(&action) {
+                    self.cast_vote(envelope.from_node_id, action, vote);
+                }
+            }
+            Message::Reply => self.receive_reply(envelope.to_node_id, envelope.from_node_id),
+        }
+    }
+
+    // Apply the node and process faults that are due
+    pub fn inject_faults(&mut self) -> Vec<Fault> {
+        let now = self.clock.now();
+        let node_ids: Vec<u32> = self
+            .nodes
+            .iter()
+            .filter(|n| n.status != NodeStatus::Halted)
+            .map(|n| n.id)
+            .collect();
+        let halted_ids: Vec<u32> = self
+            .nodes
+            .iter()
+            .filter(|n| n.status == NodeStatus::Halted)
+            .map(|n| n.id)
+            .collect();
+        let process_ids: Vec<u32> = self
+            .nodes
+            .iter()
+            .flat_map(|n| n.active_processes.iter().map(|p| p.id))
+            .collect();
+
+        let (resumed, faults) = match self.fault_injector.as_mut() {
+            Some(injector) => (injector.resume_due(now), injector.due_faults(now, &node_ids, &halted_ids, &process_ids)),
+            None => return Vec::new(),
+        };
+
+        for node_id in resumed {
+            if let Some(node) = self.nodes.iter_mut().find(|n| n.id == node_id && n.status == NodeStatus::Halted) {
+                println!("Node {} resumed after pause", node_id);
+                node.status = NodeStatus::Active;
+            }
+        }
+
+        for fault in faults.iter() {
+            self.apply_fault(fault.clone());
+        }
+        faults
+    }
+
+    fn apply_fault(&mut self, fault: Fault) {
+        println!("Injecting fault {:?}", fault);
+        match fault {
+            Fault::CrashNode { node_id } | Fault::PauseNode { node_id, .. } => {
+                if let Some(node) = self.nodes.iter_mut().find(|n| n.id == node_id) {
+                    node.status = NodeStatus::Halted;
+                }
+            }
+            Fault::FailProcess { process_id, reason } => {
+                self.fail_process(process_id, reason);
+            }
+        }
+    }
+
+    // Method to get total number of nodes
+    pub fn total_nodes(&self) -> usize {
+        self.nodes.len()
+    }    
+
+}
+
+// Lower priority processes of a node to evict so that the process fits, None if evicting them isn't enough.
+// The lowest priority and most recently placed processes go first. Paused (migrating) processes are left alone.
+fn preemption_victims(node: &Node, process: &Process) -> Option<Vec<Process>> {
+    let mut candidates: Vec<&Process> = node
+        .active_processes
+        .iter()
+        .rev()
+        .filter(|p| p.priority < process.priority && !p.paused)
+        .collect();
+    candidates.sort_by_key(|p| p.priority);
+
+    let mut available = node.available_resources.clone();
+    let mut victims = Vec::new();
+    for candidate in candidates {
+        if available.can_allocate(&process.needed_resources) {
+            break;
+        }
+        available.deallocate(&candidate.needed_resources);
+        victims.push(candidate.clone());
+    }
+    if available.can_allocate(&process.needed_resources) {
+        Some(victims)
+    } else {
+        None
+    }
 }
 
+// Split resources in `count` even shares, the remainder goes to the first shares
+fn split_resources(total: &Resources, count: usize) -> Vec<Resources> {
+    let count64 = count as u64;
+    (0..count64)
+        .map(|i| {
+            let share = |amount: u64| amount / count64 + u64::from(i < amount % count64);
+            Resources::new(share(total.ram), share(total.disk_space), share(total.threads as u64) as u32)
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::Node;
+    use crate::process::Process;
+    use crate::resource::Resources;
+    use crate::message::{Action, ActionOutcome, Request};
+    use crate::clock::Clock;
+    use crate::membership::MemberStatus;
+    use crate::fault::{Fault, FaultInjector};
+    use crate::migration::MigrationPhase;
+    use crate::join::{JoinError, JoinStatus};
+    use crate::scheduler::{BinPacking, RoundRobin};
+    use crate::process::Priority;
+    use crate::affinity::{Constraint, LabelSelector};
+    use crate::stealing::{spawn_work_stealer, WorkStealing};
+    use crate::process::ProcessState;
+    use crate::autoscale::{Autoscaler, AutoscalerConfig, ScalingEvent, StaticProvider};
+    use crate::rebalance::{spawn_rebalancer, RebalanceConfig, Rebalancer};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    use tokio::time::{sleep, Duration};
+    use crate::scheduler::{ClusterSnapshot, Rejection, Scheduler};
+
+    #[test]
+    fn test_session_new() {
+        let nodes = vec![Node::new(1), Node::new(2)];
+        let processes = vec![
+            Process::new(1, "Process 1".to_string(), Resources::new(1024, 100_000, 2)),
+            Process::new(2, "Process 2".to_string(), Resources::new(2048, 200_000, 4)),
+        ];
+        let total_resources = Resources::new(16_384, 1_000_000, 8);
+    
+        let session = Session::new(nodes.clone(), processes.clone(), total_resources.clone());
+    
+        assert_eq!(session.nodes.len(), nodes.len());
+        // The nodes share the resources evenly
+        for node in session.nodes.iter() {
+            assert_eq!(node.total_resources, Resources::new(8_192, 500_000, 4));
+        }
+        assert_eq!(session.processes, processes);
+        assert_eq!(session.total_resources, total_resources);
+        assert_eq!(session.available_resources, total_resources);
+        assert!(session.pending_votes.is_empty());
+        assert!(session.request_queue.is_empty());
+        assert!(session.deferred_replies.is_empty());
+        assert!(session.replies_received.is_empty());
+    }
+
+    #[test]
+    fn test_add_and_remove_node() {
+        let mut session = Session::new(vec![], vec![], Resources::new(0, 0, 0));
+        let node1 = Node::new(1);
+        let node2 = Node::new(2);
+    
+        session.add_node(node1.clone());
+        assert_eq!(session.nodes.len(), 1);
+        assert_eq!(session.nodes[0], node1);
+    
+        session.add_node(node2.clone());
+        assert_eq!(session.nodes.len(), 2);
+        assert_eq!(session.nodes[1], node2);
+    
+        session.remove_node(1);
+        assert_eq!(session.nodes.len(), 1);
+        assert_eq!(session.nodes[0], node2);
+    }
+
+    #[tokio::test]
+    async fn test_added_node_keeps_its_capacity() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.add_node(Node::with_resources(3, Resources::new(2_048, 10_000, 1)));
+        assert_eq!(session.nodes[2].total_resources, Resources::new(2_048, 10_000, 1));
+        assert_eq!(session.total_resources, Resources::new(18_432, 1_010_000, 9));
+
+        // A node without capacity adds nothing and can't host anything
+        session.add_node(Node::new(4));
+        assert!(session.nodes[3].total_resources.is_zero());
+        assert_eq!(session.total_resources, Resources::new(18_432, 1_010_000, 9));
+
+        session.nodes.retain(|n| n.id >= 3);
+        session.processes.push(Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2)));
+        session.assign_processes().await;
+        assert!(session.nodes.iter().all(|n| n.active_processes.is_empty()));
+        assert_eq!(session.processes[0].id, 1);
+    }
+
+    #[test]
+    fn test_total_nodes() {
+        let mut session = Session::new(vec![], vec![], Resources::new(0, 0, 0));
+        assert_eq!(session.total_nodes(), 0);
+    
+        session.add_node(Node::new(1));
+        session.add_node(Node::new(2));
+        assert_eq!(session.total_nodes(), 2);
+    
+        session.remove_node(1);
+        assert_eq!(session.total_nodes(), 1);
+    }
+
+    #[test]
+    fn test_allocate_resources_success() {
+        let total_resources = Resources::new(16_384, 1_000_000, 8);
+        let mut session = Session::new(vec![Node::new(1)], vec![], total_resources.clone());
+    
+        let needed_resources = Resources::new(4_096, 200_000, 2);
+    
+        let result = session.allocate_resources(1, &needed_resources);
+        assert!(result);
+        assert_eq!(
+            session.available_resources,
+            Resources::new(12_288, 800_000, 6)
+        );
+    }
+    
+    #[test]
+    fn test_allocate_resources_failure() {
+        let total_resources = Resources::new(16_384, 1_000_000, 8);
+        let mut session = Session::new(vec![Node::new(1)], vec![], total_resources.clone());
+    
+        let needed_resources = Resources::new(32_768, 2_000_000, 16);
+    
+        let result = session.allocate_resources(1, &needed_resources);
+        assert!(!result);
+        assert_eq!(session.available_resources, total_resources);
+    }
+
+    #[test]
+    fn test_deallocate_resources() {
+        let total_resources = Resources::new(16_384, 1_000_000, 8);
+        let mut session = Session::new(vec![Node::new(1)], vec![], total_resources.clone());
+    
+        let needed_resources = Resources::new(4_096, 200_000, 2);
+        session.allocate_resources(1, &needed_resources);
+    
+        session.deallocate_resources(1, &needed_resources);
+        assert_eq!(session.available_resources, total_resources);
+    }
+
+    #[tokio::test]
+    async fn test_assign_processes() {
+        let node1 = Node::new(1);
+        let node2 = Node::new(2);
+        let mut session = Session::new(
+            vec![node1.clone(), node2.clone()],
+            vec![],
+            Resources::new(16_384, 1_000_000, 8),
+        );
+    
+        let process1 = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 200_000, 2));
+        let process2 = Process::new(2, "Process 2".to_string(), Resources::new(4_096, 200_000, 2));
+        session.processes.push(process1.clone());
+        session.processes.push(process2.clone());
+    
+        session.assign_processes().await;
+    
+        // Check that processes have been assigned
+        let node1_processes = session.nodes.iter().find(|n| n.id == 1).unwrap().active_processes.clone();
+        let node2_processes = session.nodes.iter().find(|n| n.id == 2).unwrap().active_processes.clone();
+    
+        assert_eq!(node1_processes.len() + node2_processes.len(), 2);
+    
+        // Check that resources have been allocated
+        assert_eq!(
+            session.available_resources,
+            Resources::new(8_192, 600_000, 4)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_resource_and_can_access_resource() {
+        let node1 = Node::new(1);
+        let node2 = Node::new(2);
+        let node3 = Node::new(3);
+        let mut session = Session::new(
+            vec![node1.clone(), node2.clone(), node3.clone()],
+            vec![],
+            Resources::new(16_384, 1_000_000, 8),
+        );
+        // Both requests get the same timestamp, the tie is broken by node id
+        session.clock = Clock::simulated(0);
+    
+        // Node 1 requests resource
+        session.request_resource(1).await;
+        // Since no other nodes have requested, Node 1 should have access
+        assert!(session.can_access_resource(1));
+    
+        // Node 2 requests resource
+        session.request_resource(2).await;
+        // Node 2 should have access
+        assert!(session.can_access_resource(2));
+    
+        // Node 1 releases resource
+        session.release_resource(1);
+    
+        // Now Node 2 should have access
+        assert!(session.can_access_resource(2));
+    }
+
+    #[test]
+    fn test_handle_request_and_should_reply_immediately() {
+        let node1 = Node::new(1);
+        let node2 = Node::new(2);
+        let mut session = Session::new(vec![node1.clone(), node2.clone()], vec![], Resources::new(0, 0, 0));
+    
+        // Simulate Node 1's request
+        let request1 = Request {
+            from_node_id: 1,
+            timestamp: 100,
+        };
+        session.request_queue.push_back(request1.clone());
+    
+        // Node 2 receives Node 1's request
+        session.handle_request(2, request1.clone());
+    
+        // Since Node 2 hasn't requested, it should send a reply immediately
+        assert_eq!(session.replies_received.get(&1).unwrap().len(), 1);
+        assert_eq!(session.replies_received.get(&1).unwrap()[0], 2);
+    
+        // Now, Node 2 requests the resource with a higher timestamp
+        let request2 = Request {
+            from_node_id: 2,
+            timestamp: 200,
+        };
+        session.request_queue.push_back(request2.clone());
+    
+        // Node 1 handles Node 2's request
+        session.handle_request(1, request2.clone());
+    
+        // Node 1 should defer the reply since its request has a lower timestamp
+        assert!(session.deferred_replies.get(&1).unwrap().len() == 1);
+    }
+
+    #[test]
+    fn test_release_resource_and_deferred_replies() {
+        let node1 = Node::new(1);
+        let node2 = Node::new(2);
+        let mut session = Session::new(vec![node1.clone(), node2.clone()], vec![], Resources::new(0, 0, 0));
+    
+        // Node 1 requests resource
+        let request1 = Request {
+            from_node_id: 1,
+            timestamp: 100,
+        };
+        session.request_queue.push_back(request1.clone());
+    
+        // Node 2 handles Node 1's request and replies immediately
+        session.handle_request(2, request1.clone());
+    
+        // Node 2 requests resource
+        let request2 = Request {
+            from_node_id: 2,
+            timestamp: 200,
+        };
+        session.request_queue.push_back(request2.clone());
+    
+        // Node 1 handles Node 2's request and defers reply
+        session.handle_request(1, request2.clone());
+    
+        // Node 1 releases resource
+        session.release_resource(1);
+    
+        // Node 1 should send the deferred reply to Node 2
+        assert_eq!(session.replies_received.get(&2).unwrap().len(), 1);
+        assert_eq!(session.replies_received.get(&2).unwrap()[0], 1);
+    }
+
+    #[test]
+    fn test_record_heartbeat_and_suspicion_level() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(0, 0, 0));
+        session.clock = Clock::simulated(0);
+        assert_eq!(session.suspicion_level(2), None);
+
+        for _ in 0..10 {
+            session.clock.advance(1_000);
+            session.record_heartbeat(1);
+            session.record_heartbeat(2);
+        }
+        assert_eq!(session.nodes[1].last_heartbeat, 10_000);
+        assert!(session.suspicion_level(2).unwrap() < 1.0);
+
+        session.clock.advance(10_000);
+        assert!(session.suspicion_level(2).unwrap() > 8.0);
+    }
+
+    #[test]
+    fn test_node_failure_voting_consults_failure_detector() {
+        let mut session = Session::new(
+            vec![Node::new(1), Node::new(2), Node::new(3)],
+            vec![],
+            Resources::new(0, 0, 0),
+        );
+        session.clock = Clock::simulated(0);
+
+        for _ in 0..10 {
+            session.clock.advance(1_000);
+            for id in 1..=3 {
+                session.record_heartbeat(id);
+            }
+        }
+        // Node 3 stops sending heartbeats
+        for _ in 0..10 {
+            session.clock.advance(1_000);
+            session.record_heartbeat(1);
+            session.record_heartbeat(2);
+        }
+
+        // Node 2 is healthy: the failure report is rejected
+        let healthy = Action::NodeFailure { node_id: 2, reason: "Timeout".to_string() };
+        session.initiate_voting(1, healthy.clone());
+        assert!(!session.pending_votes.contains_key(&healthy));
+        assert!(!session.nodes[0].failure_detector.is_suspected(2).unwrap());
+
+        // Node 3 is suspected: the failure report is approved and the node reinstalled
+        let silent = Action::NodeFailure { node_id: 3, reason: "Timeout".to_string() };
+        session.initiate_voting(1, silent.clone());
+        assert!(!session.pending_votes.contains_key(&silent));
+        assert_eq!(session.suspicion_level(3), None);
+    }
+
+    fn converged_session(node_count: u32) -> Session {
+        let nodes = (1..=node_count).map(Node::new).collect();
+        let mut session = Session::new(nodes, vec![], Resources::new(0, 0, 0));
+        session.clock = Clock::simulated(0);
+        for _ in 0..10 {
+            session.clock.advance(1_000);
+            session.swim_round();
+        }
+        session
+    }
+
+    #[test]
+    fn test_swim_membership_converges() {
+        let session = converged_session(5);
+
+        for node in session.nodes.iter() {
+            let expected: Vec<u32> = (1..=5).filter(|&id| id != node.id).collect();
+            assert_eq!(node.membership.live_members(), expected);
+        }
+    }
+
+    #[test]
+    fn test_swim_detects_halted_node() {
+        let mut session = converged_session(4);
+        session.nodes[2].status = NodeStatus::Halted;
+
+        for _ in 0..15 {
+            session.clock.advance(1_000);
+            session.swim_round();
+        }
+
+        for node in session.nodes.iter().filter(|n| n.id != 3) {
+            assert_eq!(node.membership.status_of(3), Some(MemberStatus::Dead));
+        }
+    }
+
+    #[test]
+    fn test_swim_gossips_join_and_leave() {
+        let mut session = converged_session(4);
+
+        session.add_node(Node::new(5));
+        session.remove_node(2);
+        for _ in 0..10 {
+            session.clock.advance(1_000);
+            session.swim_round();
+        }
+
+        for node in session.nodes.iter() {
+            let expected: Vec<u32> = [1, 3, 4, 5].into_iter().filter(|&id| id != node.id).collect();
+            assert_eq!(node.membership.live_members(), expected);
+            if node.id != 5 {
+                assert_eq!(node.membership.status_of(2), Some(MemberStatus::Left));
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_node_recovers_after_catch_up() {
+        let mut session = converged_session(3);
+        let process = Process::new(7, "Process 7".to_string(), Resources::new(0, 0, 0));
+        session.nodes[1].active_processes.push(process);
+
+        let action = Action::NodeFailure { node_id: 2, reason: "Timeout".to_string() };
+        session.initiate_voting(1, action.clone());
+        let node = session.nodes.iter().find(|n| n.id == 2).unwrap();
+        assert_eq!(node.status, NodeStatus::Recovering);
+
+        // The reinstalled node is back once it sends a heartbeat
+        session.record_heartbeat(2);
+        session.clock.advance(1_000);
+        assert_eq!(session.advance_recoveries(), vec![(2, RecoveryOutcome::Recovered)]);
+
+        let node = session.nodes.iter().find(|n| n.id == 2).unwrap();
+        assert_eq!(node.status, NodeStatus::Active);
+        assert!(node.known_actions.contains_key(&action));
+        assert_eq!(node.membership.live_members(), vec![1, 3]);
+        assert!(node.membership.incarnation > 0);
+        assert!(session.recoveries.is_empty());
+        // The process was rescheduled on a surviving node
+        assert!(session.processes.is_empty());
+        assert!(session.nodes.iter().any(|n| n.id != 2 && n.active_processes.iter().any(|p| p.id == 7)));
+    }
+
+    #[test]
+    fn test_recovery_retries_with_backoff_then_gives_up() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(0, 0, 0));
+        session.clock = Clock::simulated(0);
+
+        session.handle_node_failure(2, "Disk failure".to_string());
+
+        // The first attempt waits for the initial backoff
+        let node = session.nodes.iter().find(|n| n.id == 2).unwrap();
+        assert_eq!(node.status, NodeStatus::Recovering);
+        assert_eq!(session.recoveries[&2].attempts, 0);
+        assert_eq!(session.recoveries[&2].next_attempt_at, 1_000);
+
+        session.clock.advance(500);
+        assert!(session.advance_recoveries().is_empty());
+
+        // The node never sends a heartbeat
+        session.clock.advance(500);
+        assert_eq!(
+            session.advance_recoveries(),
+            vec![(2, RecoveryOutcome::Retrying { next_attempt_at: 2_000 })]
+        );
+        session.clock.advance(1_000);
+        assert_eq!(
+            session.advance_recoveries(),
+            vec![(2, RecoveryOutcome::Retrying { next_attempt_at: 4_000 })]
+        );
+
+        session.clock.advance(2_000);
+        assert_eq!(session.advance_recoveries(), vec![(2, RecoveryOutcome::GaveUp)]);
+        assert!(!session.nodes.iter().any(|n| n.id == 2));
+        assert!(session.recoveries.is_empty());
+    }
+
+    #[test]
+    fn test_recovering_node_drops_processes_taken_over() {
+        let mut session = converged_session(3);
+        // Node 2 comes back still holding process 7, which now runs on node 1, and process 8
+        session.nodes[0].active_processes.push(Process::new(7, "Process 7".to_string(), Resources::new(0, 0, 0)));
+        session.nodes[1].status = NodeStatus::Recovering;
+        session.nodes[1].active_processes.push(Process::new(7, "Process 7".to_string(), Resources::new(0, 0, 0)));
+        session.nodes[1].active_processes.push(Process::new(8, "Process 8".to_string(), Resources::new(0, 0, 0)));
+        let now = session.clock.now();
+        session.recoveries.insert(2, RecoveryState::new(2, vec![7, 8], Resources::new(0, 0, 0), now));
+        session.record_heartbeat(2);
+
+        assert_eq!(session.attempt_recovery(2), Some(RecoveryOutcome::Recovered));
+
+        let running: Vec<u32> = session.nodes[1].active_processes.iter().map(|p| p.id).collect();
+        assert_eq!(running, vec![8]);
+        assert_eq!(session.nodes[0].active_processes[0].id, 7);
+    }
+
+    #[tokio::test]
+    async fn test_recovering_node_is_not_scheduled() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.nodes[1].status = NodeStatus::Recovering;
+        session.processes.push(Process::new(1, "Process 1".to_string(), Resources::new(1_024, 1_000, 1)));
+        session.processes.push(Process::new(2, "Process 2".to_string(), Resources::new(1_024, 1_000, 1)));
+
+        session.assign_processes().await;
+
+        assert_eq!(session.nodes[0].active_processes.len(), 2);
+        assert!(session.nodes[1].active_processes.is_empty());
+    }
+
+    #[test]
+    fn test_minority_partition_cannot_execute_actions() {
+        let nodes = (1..=5).map(Node::new).collect();
+        let mut session = Session::new(nodes, vec![], Resources::new(0, 0, 0));
+        session.clock = Clock::simulated(0);
+        session.network.partition(vec![vec![1, 2, 3], vec![4, 5]]);
+
+        assert!(session.has_quorum(1));
+        assert!(!session.has_quorum(4));
+
+        // The minority side can't vote out a node of the majority
+        let action = Action::NodeFailure { node_id: 1, reason: "Unreachable".to_string() };
+        session.initiate_voting(4, action);
+        assert!(session.committed_actions.is_empty());
+        assert_eq!(session.nodes[0].status, NodeStatus::Active);
+
+        // The majority side can
+        let action = Action::NodeFailure { node_id: 4, reason: "Unreachable".to_string() };
+        session.initiate_voting(1, action.clone());
+        assert_eq!(session.committed_actions, vec![action]);
+
+        // Node 4 can't pass its health checks while it is cut off from the majority
+        session.record_heartbeat(4);
+        session.clock.advance(1_000);
+        assert_eq!(session.advance_recoveries(), vec![(4, RecoveryOutcome::Retrying { next_attempt_at: 2_000 })]);
+        let node = session.nodes.iter().find(|n| n.id == 4).unwrap();
+        assert_eq!(node.status, NodeStatus::Recovering);
+
+        session.network.heal();
+        session.clock.advance(1_000);
+        assert_eq!(session.advance_recoveries(), vec![(4, RecoveryOutcome::Recovered)]);
+    }
+
+    #[tokio::test]
+    async fn test_minority_partition_stops_scheduling() {
+        let nodes = (1..=5).map(Node::new).collect();
+        let mut session = Session::new(nodes, vec![], Resources::new(16_384, 1_000_000, 8));
+        session.network.partition(vec![vec![1, 2, 3], vec![4, 5]]);
+
+        for id in 1..=5 {
+            session.processes.push(Process::new(id, format!("Process {}", id), Resources::new(1_024, 1_000, 1)));
+        }
+        session.assign_processes().await;
+
+        assert!(session.processes.is_empty());
+        assert!(session.nodes[3].active_processes.is_empty());
+        assert!(session.nodes[4].active_processes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_partition_blocks_mutual_exclusion() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(0, 0, 0));
+        session.network.partition(vec![vec![1, 2], vec![3]]);
+
+        // Node 3 can't answer, so access can't be granted safely
+        session.request_resource(1).await;
+        assert!(!session.can_access_resource(1));
+    }
+
+    #[test]
+    fn test_cast_vote_counts_each_node_once() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(0, 0, 0));
+        let action = Action::RedistributeProcess { process_id: 1, target_node_id: None };
+
+        session.pending_votes.insert(action.clone(), (1, vec![]));
+
+        session.cast_vote(2, action.clone(), Vote::Approve);
+        session.cast_vote(2, action.clone(), Vote::Approve);
+        assert_eq!(session.pending_votes[&action].1.len(), 1);
+
+        // Votes on an action nobody proposed are ignored
+        let unknown = Action::RedistributeProcess { process_id: 2, target_node_id: None };
+        session.cast_vote(2, unknown.clone(), Vote::Approve);
+        assert!(!session.pending_votes.contains_key(&unknown));
+    }
+
+    #[test]
+    fn test_outcome_goes_to_proposer_whatever_the_vote_order() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(0, 0, 0));
+        let action = Action::RedistributeProcess { process_id: 7, target_node_id: None };
+        // The votes of nodes 1 and 2 were delivered before the proposer's own
+        session.pending_votes.insert(action.clone(), (3, vec![(1, Vote::Approve), (2, Vote::Approve), (3, Vote::Approve)]));
+
+        session.check_consensus(action.clone());
+
+        assert!(session.nodes[0].action_outcomes.is_empty());
+        assert_eq!(session.nodes[2].action_outcomes.len(), 1);
+        assert_eq!(session.nodes[2].action_outcomes[0].0, action);
+    }
+
+    #[test]
+    fn test_dropped_votes_prevent_consensus() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(0, 0, 0));
+        let mut injector = FaultInjector::new(1);
+        injector.drop_probability = 1.0;
+        session.fault_injector = Some(injector);
+
+        let action = Action::ProcessFailure { node_id: 2, reason: "Timeout".to_string() };
+        session.initiate_voting(1, action.clone());
+
+        assert!(session.committed_actions.is_empty());
+        assert_eq!(session.pending_votes[&action].1.len(), 1);
+    }
+
+    #[test]
+    fn test_delayed_votes_reach_consensus_later() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(0, 0, 0));
+        session.clock = Clock::simulated(0);
+        let mut injector = FaultInjector::new(1);
+        injector.delay_probability = 1.0;
+        injector.max_delay = 500;
+        session.fault_injector = Some(injector);
+
+        let action = Action::ProcessFailure { node_id: 5, reason: "Timeout".to_string() };
+        session.initiate_voting(1, action.clone());
+        assert!(session.committed_actions.is_empty());
+
+        session.clock.advance(500);
+        session.deliver_messages();
+        assert_eq!(session.committed_actions, vec![action]);
+        assert!(session.network.in_flight.is_empty());
+    }
+
+    #[test]
+    fn test_duplicated_replies_counted_once() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(0, 0, 0));
+        let mut injector = FaultInjector::new(1);
+        injector.duplicate_probability = 1.0;
+        session.fault_injector = Some(injector);
+
+        session.send_reply(1, 2);
+        assert_eq!(session.replies_received[&1], vec![2]);
+        assert!(!session.can_access_resource(1));
+    }
+
+    #[test]
+    fn test_inject_node_and_process_faults() {
+        let mut session = Session::new(
+            vec![Node::new(1), Node::new(2), Node::new(3)],
+            vec![],
+            Resources::new(16_384, 1_000_000, 8),
+        );
+        session.clock = Clock::simulated(0);
+        let process = Process::new(9, "Process 9".to_string(), Resources::new(4_096, 1_000, 2));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process);
+
+        let mut injector = FaultInjector::new(1);
+        injector.schedule(100, Fault::CrashNode { node_id: 2 });
+        injector.schedule(100, Fault::PauseNode { node_id: 3, duration: 400 });
+        injector.schedule(200, Fault::FailProcess { process_id: 9, reason: "Injected".to_string() });
+        session.fault_injector = Some(injector);
+
+        session.clock.advance(100);
+        assert_eq!(session.inject_faults().len(), 2);
+        assert_eq!(session.nodes[1].status, NodeStatus::Halted);
+        assert_eq!(session.nodes[2].status, NodeStatus::Halted);
+
+        session.clock.advance(100);
+        session.inject_faults();
+        assert!(session.nodes[0].active_processes.is_empty());
+        assert_eq!(session.available_resources, session.total_resources);
+
+        session.clock.advance(300);
+        session.inject_faults();
+        assert_eq!(session.nodes[1].status, NodeStatus::Halted);
+        assert_eq!(session.nodes[2].status, NodeStatus::Active);
+    }
+
+    #[test]
+    fn test_pause_after_crash_keeps_node_down() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.clock = Clock::simulated(0);
+        let mut injector = FaultInjector::new(1);
+        injector.schedule(1, Fault::CrashNode { node_id: 2 });
+        injector.schedule(2, Fault::PauseNode { node_id: 2, duration: 100 });
+        session.fault_injector = Some(injector);
+
+        session.clock.advance(1);
+        session.inject_faults();
+        session.clock.advance(1);
+        assert!(session.inject_faults().is_empty());
+
+        // The pause would have ended at 102
+        session.clock.advance(200);
+        session.inject_faults();
+        assert_eq!(session.nodes[1].status, NodeStatus::Halted);
+    }
+
+    #[test]
+    fn test_redistribute_process_reports_to_proposer() {
+        let mut session = Session::new(
+            vec![Node::new(1), Node::new(2), Node::new(3)],
+            vec![],
+            Resources::new(16_384, 1_000_000, 8),
+        );
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process.clone());
+        session.nodes[1].active_processes.push(Process::new(2, "Process 2".to_string(), Resources::new(0, 0, 0)));
+
+        let action = Action::RedistributeProcess { process_id: 1, target_node_id: None };
+        session.initiate_voting(2, action.clone());
+
+        assert!(session.nodes[0].active_processes.is_empty());
+        assert_eq!(session.nodes[2].active_processes, vec![process]);
+        // The reservation moved with the process
+        assert_eq!(session.available_resources, Resources::new(12_288, 999_000, 6));
+        assert_eq!(
+            session.nodes[1].action_outcomes,
+            vec![(action, ActionOutcome::Redistributed { process_id: 1, from_node_id: Some(1), to_node_id: 3 })]
+        );
+    }
+
+    #[test]
+    fn test_redistribute_process_without_capacity() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.nodes[0].active_processes.push(Process::new(1, "Process 1".to_string(), Resources::new(0, 0, 0)));
+        session.nodes[1].status = NodeStatus::Recovering;
+
+        assert_eq!(session.redistribute_process(1, None), ActionOutcome::NoCapacity { process_id: 1 });
+        assert_eq!(session.nodes[0].active_processes.len(), 1);
+        assert_eq!(session.redistribute_process(5, None), ActionOutcome::ProcessNotFound { process_id: 5 });
+    }
+
+    #[test]
+    fn test_redistribute_pending_process() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.processes.push(Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2)));
+
+        assert_eq!(
+            session.redistribute_process(1, None),
+            ActionOutcome::Redistributed { process_id: 1, from_node_id: None, to_node_id: 1 }
+        );
+        assert!(session.processes.is_empty());
+        assert_eq!(session.available_resources, Resources::new(12_288, 999_000, 6));
+    }
+
+    #[test]
+    fn test_redistribute_pending_process_that_cant_start() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(16_384, 1_000_000, 8));
+        let mut process = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2));
+        process.transition(ProcessState::Cancelled, 0).unwrap();
+        session.processes.push(process);
+
+        assert_eq!(session.redistribute_process(1, None), ActionOutcome::NoCapacity { process_id: 1 });
+        // Still queued, nothing reserved
+        assert_eq!(session.processes[0].id, 1);
+        assert!(session.nodes[0].active_processes.is_empty());
+        assert_eq!(session.available_resources, Resources::new(16_384, 1_000_000, 8));
+    }
+
+    #[test]
+    fn test_redistributed_process_takes_its_command_along() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process);
+        let (kill, mut killed) = tokio::sync::oneshot::channel();
+        session.running_commands.insert(1, RunningCommand { node_id: 1, started_at: None, kill });
+
+        assert_eq!(
+            session.redistribute_process(1, None),
+            ActionOutcome::Redistributed { process_id: 1, from_node_id: Some(1), to_node_id: 2 }
+        );
+        assert_eq!(session.running_commands[&1].node_id, 2);
+        // The command wasn't killed
+        assert_eq!(killed.try_recv(), Err(tokio::sync::oneshot::error::TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_live_migration_phases() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(16_384, 1_000_000, 8));
+        let mut process = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2));
+        process.started_at = Some(42);
+        session.nodes[0].active_processes.push(process);
+
+        let action = Action::MigrateProcess { process_id: 1, target_node_id: 3 };
+        session.initiate_voting(2, action.clone());
+        assert_eq!(session.migrations[0].phase, MigrationPhase::PreCopy);
+
+        // Pre-copy: the process keeps running on the source
+        session.advance_migrations();
+        assert!(!session.nodes[0].active_processes[0].paused);
+        while session.migrations[0].phase == MigrationPhase::PreCopy {
+            session.advance_migrations();
+        }
+        assert_eq!(session.migrations[0].phase, MigrationPhase::Paused);
+        assert!(session.nodes[0].active_processes[0].paused);
+
+        session.advance_migrations(); // stop and copy
+        session.advance_migrations(); // cutover
+        assert_eq!(session.migrations[0].phase, MigrationPhase::Cleanup);
+        assert!(!session.nodes[2].active_processes[0].paused);
+
+        session.advance_migrations(); // cleanup
+        assert_eq!(session.migrations[0].phase, MigrationPhase::Completed);
+        assert!(session.nodes[0].active_processes.is_empty());
+        // Same run, not restarted on the target
+        assert_eq!(session.nodes[2].active_processes[0].started_at, Some(42));
+        assert_eq!(
+            session.nodes[1].action_outcomes,
+            vec![(action, ActionOutcome::Migrated { process_id: 1, from_node_id: 1, to_node_id: 3 })]
+        );
+    }
+
+    #[test]
+    fn test_live_migration_aborts_when_target_fails() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.nodes[0].active_processes.push(Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2)));
+        session.migration_config.max_precopy_rounds = 1;
+
+        session.start_migration(1, 1, 2);
+        session.advance_migrations();
+        assert!(session.nodes[0].active_processes[0].paused);
+
+        session.nodes[1].status = NodeStatus::Halted;
+        session.advance_migrations();
+
+        // The process resumes on the source
+        assert!(matches!(session.migrations[0].phase, MigrationPhase::Aborted { .. }));
+        assert!(!session.nodes[0].active_processes[0].paused);
+        assert!(session.nodes[1].active_processes.is_empty());
+        assert!(matches!(session.nodes[0].action_outcomes[0].1, ActionOutcome::MigrationAborted { .. }));
+    }
+
+    #[test]
+    fn test_target_failure_after_cutover_keeps_single_copy() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process);
+        session.migration_config.max_precopy_rounds = 1;
+
+        session.start_migration(1, 1, 2);
+        session.advance_migrations(); // pre-copy
+        session.advance_migrations(); // stop and copy
+        session.advance_migrations(); // cutover
+        assert_eq!(session.migrations[0].phase, MigrationPhase::Cleanup);
+        assert!(session.nodes[0].active_processes.is_empty());
+
+        // The target goes down before the source is cleaned up
+        session.nodes[1].status = NodeStatus::Halted;
+        session.advance_migrations();
+
+        assert_eq!(session.migrations[0].phase, MigrationPhase::Completed);
+        assert!(session.nodes[0].active_processes.is_empty());
+        assert_eq!(session.nodes[1].active_processes[0].id, 1);
+        assert_eq!(session.nodes[0].available_resources, session.nodes[0].total_resources);
+    }
+
+    #[test]
+    fn test_resources_split_among_nodes() {
+        let shares = split_resources(&Resources::new(10, 7, 5), 3);
+
+        assert_eq!(
+            shares,
+            vec![Resources::new(4, 3, 2), Resources::new(3, 2, 2), Resources::new(3, 2, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_processes_only_go_to_nodes_with_room() {
+        let small = Node::with_resources(1, Resources::new(4_096, 100_000, 2));
+        let big = Node::with_resources(2, Resources::new(32_768, 1_000_000, 16));
+        let mut session = Session::new(vec![small, big], vec![], Resources::new(0, 0, 0));
+        assert_eq!(session.total_resources, Resources::new(36_864, 1_100_000, 18));
+
+        // Node 1 is the least loaded, but only node 2 can hold 16 GB
+        session.nodes[1].active_processes.push(Process::new(9, "Process 9".to_string(), Resources::new(0, 0, 0)));
+        session.processes.push(Process::new(1, "Process 1".to_string(), Resources::new(16_384, 10_000, 4)));
+        session.assign_processes().await;
+
+        assert!(session.nodes[0].active_processes.is_empty());
+        assert_eq!(session.nodes[1].available_resources, Resources::new(16_384, 990_000, 12));
+        assert_eq!(session.available_resources, Resources::new(20_480, 1_090_000, 14));
+    }
+
+    #[tokio::test]
+    async fn test_pluggable_scheduler() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(30_000, 30_000, 30));
+        session.scheduler = Box::new(RoundRobin::default());
+        for id in 1..=4 {
+            session.processes.push(Process::new(id, format!("Process {}", id), Resources::new(1_000, 1_000, 1)));
+        }
+
+        session.assign_processes().await;
+
+        let placed: Vec<usize> = session.nodes.iter().map(|n| n.active_processes.len()).collect();
+        assert_eq!(placed, vec![2, 1, 1]);
+        assert_eq!(session.nodes[0].active_processes[1].id, 4);
+    }
+
+    #[tokio::test]
+    async fn test_batch_bin_packing_avoids_fragmentation() {
+        let mut session = Session::new(
+            vec![Node::with_resources(1, Resources::new(8_192, 100_000, 4)), Node::with_resources(2, Resources::new(4_096, 100_000, 4))],
+            vec![],
+            Resources::new(0, 0, 0),
+        );
+        session.scheduler = Box::new(BinPacking { batch: true });
+        session.processes.push(Process::new(1, "Small".to_string(), Resources::new(4_096, 1_000, 1)));
+        session.processes.push(Process::new(2, "Big".to_string(), Resources::new(8_192, 1_000, 1)));
+
+        session.assign_processes().await;
+
+        assert!(session.processes.is_empty());
+        assert_eq!(session.nodes[0].active_processes[0].id, 2);
+        assert_eq!(session.nodes[1].active_processes[0].id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pending_queue_ordered_by_priority() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(4_096, 10_000, 2));
+        session.preemption = false;
+        session.processes.push(Process::new(1, "Normal".to_string(), Resources::new(4_096, 1_000, 1)));
+        session.processes.push(Process::with_priority(2, "Critical".to_string(), Resources::new(4_096, 1_000, 1), Priority::Critical));
+        session.processes.push(Process::with_priority(3, "Batch".to_string(), Resources::new(4_096, 1_000, 1), Priority::BestEffort));
+
+        session.assign_processes().await;
+
+        assert_eq!(session.nodes[0].active_processes[0].id, 2);
+        let pending: Vec<u32> = session.processes.iter().map(|p| p.id).collect();
+        assert_eq!(pending, vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_preemption_requeues_lower_priority_processes() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(8_192, 20_000, 4));
+        for (id, node_id, priority) in [(1, 1, Priority::BestEffort), (2, 1, Priority::Normal), (3, 2, Priority::Critical)] {
+            let process = Process::with_priority(id, format!("Process {}", id), Resources::new(2_048, 1_000, 1), priority);
+            session.allocate_resources(node_id, &process.needed_resources);
+            session.nodes[node_id as usize - 1].start_process(process, 0).unwrap();
+        }
+
+        // Needs a whole node: only node 1 runs processes it can preempt
+        session.processes.push(Process::with_priority(4, "Urgent".to_string(), Resources::new(4_096, 2_000, 2), Priority::Critical));
+        session.assign_processes().await;
+
+        let on_node1: Vec<u32> = session.nodes[0].active_processes.iter().map(|p| p.id).collect();
+        assert_eq!(on_node1, vec![4]);
+        assert_eq!(session.nodes[1].active_processes[0].id, 3);
+        let requeued: Vec<u32> = session.processes.iter().map(|p| p.id).collect();
+        assert_eq!(requeued, vec![2, 1]);
+        assert!(session.processes.iter().all(|p| p.state == ProcessState::Pending));
+        assert_eq!(session.nodes[0].available_resources, Resources::new(0, 8_000, 0));
+    }
+
+    #[tokio::test]
+    async fn test_no_preemption_of_equal_priority() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(4_096, 10_000, 2));
+        let process = Process::new(1, "Running".to_string(), Resources::new(4_096, 1_000, 1));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process);
+
+        session.processes.push(Process::new(2, "Waiting".to_string(), Resources::new(4_096, 1_000, 1)));
+        session.assign_processes().await;
+
+        assert_eq!(session.nodes[0].active_processes[0].id, 1);
+        assert_eq!(session.processes[0].id, 2);
+    }
+
+    #[test]
+    fn test_failed_start_releases_reservation() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(4_096, 10_000, 2));
+        let mut process = Process::new(1, "Job".to_string(), Resources::new(2_048, 1_000, 1));
+        process.transition(ProcessState::Cancelled, 0).unwrap();
+
+        assert!(!session.bind_process(1, &process));
+        assert!(session.nodes[0].active_processes.is_empty());
+        assert_eq!(session.nodes[0].available_resources, Resources::new(4_096, 10_000, 2));
+        assert_eq!(session.available_resources, Resources::new(4_096, 10_000, 2));
+    }
+
+    #[tokio::test]
+    async fn test_placement_constraints() {
+        let mut session = Session::new(
+            (1..=4).map(Node::new).collect(),
+            vec![],
+            Resources::new(32_768, 400_000, 16),
+        );
+        for (node, zone, disk) in [(0, "a", "hdd"), (1, "a", "ssd"), (2, "b", "hdd"), (3, "b", "ssd")] {
+            session.nodes[node].labels.insert("zone".to_string(), zone.to_string());
+            session.nodes[node].labels.insert("disk".to_string(), disk.to_string());
+        }
+
+        // Two replicas kept apart, in zone b, preferably on ssd
+        for id in 1..=2 {
+            let mut replica = Process::new(id, format!("Replica {}", id), Resources::new(1_024, 1_000, 1));
+            replica.constraints = vec![
+                Constraint::RequiredAffinity(LabelSelector::new("zone", &["b"])),
+                Constraint::PreferredAffinity(LabelSelector::new("disk", &["ssd"])),
+                Constraint::AvoidProcesses { process_ids: vec![3 - id] },
+            ];
+            session.processes.push(replica);
+        }
+        // A third replica has no node left in zone b
+        let mut extra = Process::new(3, "Replica 3".to_string(), Resources::new(1_024, 1_000, 1));
+        extra.constraints = vec![
+            Constraint::RequiredAffinity(LabelSelector::new("zone", &["b"])),
+            Constraint::AvoidProcesses { process_ids: vec![1, 2] },
+        ];
+        session.processes.push(extra);
+
+        session.assign_processes().await;
+
+        assert_eq!(session.nodes[3].active_processes[0].id, 1);
+        assert_eq!(session.nodes[2].active_processes[0].id, 2);
+        assert!(session.nodes[0].active_processes.is_empty() && session.nodes[1].active_processes.is_empty());
+        assert_eq!(session.processes[0].id, 3);
+        let rejection = session.cluster_snapshot().rejection(&session.processes[0]);
+        assert_eq!(rejection, Rejection::ConstraintsUnsatisfied { process_id: 3 });
+    }
+
+    #[tokio::test]
+    async fn test_workflow_runs_tasks_in_dependency_order() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 100_000, 8));
+        let resources = Resources::new(1_024, 1_000, 1);
+        let mut load = Task::with_dependencies(2, "Load".to_string(), resources.clone(), vec![1]);
+        load.max_retries = 1;
+        let workflow = Workflow::new(7, vec![
+            Task::new(1, "Extract".to_string(), resources.clone()),
+            load,
+            Task::with_dependencies(3, "Report".to_string(), resources.clone(), vec![2]),
+        ]).unwrap();
+
+        // Only the task without dependencies is queued
+        session.submit_workflow(workflow);
+        let queued: Vec<String> = session.processes.iter().map(|p| p.task.clone()).collect();
+        assert_eq!(queued, vec!["Extract".to_string()]);
+
+        session.assign_processes().await;
+        assert!(session.complete_process(1));
+        // Load fails once and is retried as a new process
+        session.assign_processes().await;
+        assert!(session.fail_process(2, "Disk full".to_string()));
+        session.assign_processes().await;
+        assert!(session.complete_process(3));
+        session.assign_processes().await;
+        assert!(session.complete_process(4));
+
+        let report = session.workflow_report(7).unwrap();
+        assert_eq!(report.status, WorkflowStatus::Succeeded);
+        assert_eq!(report.attempts, vec![(1, 1), (2, 2), (3, 1)]);
+        assert_eq!(session.available_resources, session.total_resources);
+    }
+
+    #[test]
+    fn test_workflow_task_ids_skip_locally_queued_processes() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(16_384, 100_000, 8));
+        session.dispatch_process(1, Process::new(5, "Queued".to_string(), Resources::new(1_024, 1_000, 1)));
+
+        let workflow = Workflow::new(1, vec![Task::new(1, "Build".to_string(), Resources::new(1_024, 1_000, 1))]).unwrap();
+        session.submit_workflow(workflow);
+
+        assert_eq!(session.processes[0].id, 6);
+        assert_eq!(session.last_process_id, 6);
+    }
+
+    #[test]
+    fn test_workflow_task_ids_skip_finished_and_queued_processes() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(16_384, 100_000, 8));
+        session.dispatch_process(1, Process::new(5, "Queued".to_string(), Resources::new(1_024, 1_000, 1)));
+        session.dispatch_process(1, Process::new(7, "Cancelled".to_string(), Resources::new(1_024, 1_000, 1)));
+        assert!(session.cancel_process(7));
+        assert_eq!(session.finished_processes[0].id, 7);
+
+        let workflow = Workflow::new(1, vec![Task::new(1, "Build".to_string(), Resources::new(1_024, 1_000, 1))]).unwrap();
+        session.submit_workflow(workflow);
+
+        assert_eq!(session.processes[0].id, 8);
+        assert_eq!(session.last_process_id, 8);
+    }
+
+    #[tokio::test]
+    async fn test_workflow_failure_skips_dependent_tasks() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(16_384, 100_000, 8));
+        let resources = Resources::new(1_024, 1_000, 1);
+        let workflow = Workflow::new(1, vec![
+            Task::new(1, "Build".to_string(), resources.clone()),
+            Task::with_dependencies(2, "Deploy".to_string(), resources.clone(), vec![1]),
+        ]).unwrap();
+        session.submit_workflow(workflow);
+        session.assign_processes().await;
+
+        session.fail_process(1, "Compilation error".to_string());
+
+        let report = session.workflow_report(1).unwrap();
+        assert_eq!(report.status, WorkflowStatus::Failed);
+        assert_eq!(report.failed, vec![(1, "Compilation error".to_string())]);
+        assert_eq!(report.skipped, vec![2]);
+        assert!(session.processes.is_empty());
+    }
+
+    // Sends every process to the same node, whether it fits or not
+    struct SingleNode(u32);
+
+    impl Scheduler for SingleNode {
+        fn name(&self) -> &str {
+            "single-node"
+        }
+
+        fn place(&mut self, _process: &Process, _cluster: &ClusterSnapshot) -> Result<u32, Rejection> {
+            Ok(self.0)
+        }
+    }
+
+    fn gang(job_id: u32, size: u32, resources: Resources) -> Job {
+        let processes = (1..=size)
+            .map(|i| Process::new(job_id * 10 + i, format!("Worker {}", i), resources.clone()))
+            .collect();
+        Job::new(job_id, processes)
+    }
+
+    #[tokio::test]
+    async fn test_gang_is_placed_across_nodes() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(12_288, 30_000, 6));
+        session.submit_job(gang(1, 3, Resources::new(4_096, 10_000, 2)));
+
+        session.assign_processes().await;
+
+        assert!(session.jobs.is_empty());
+        assert!(session.nodes.iter().all(|n| n.active_processes.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn test_gang_that_does_not_fit_waits_whole() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(8_192, 20_000, 4));
+        session.submit_job(gang(1, 3, Resources::new(4_096, 10_000, 2)));
+        session.processes.push(Process::new(1, "Single".to_string(), Resources::new(1_024, 1_000, 1)));
+
+        session.assign_processes().await;
+
+        // None of the gang runs, the single process still gets placed
+        assert_eq!(session.jobs.len(), 1);
+        let running: Vec<u32> = session.nodes.iter().flat_map(|n| n.active_processes.iter()).map(|p| p.id).collect();
+        assert_eq!(running, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_gang_rolls_back_partial_reservations() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(8_192, 20_000, 4));
+        session.scheduler = Box::new(SingleNode(1));
+        session.submit_job(gang(1, 2, Resources::new(4_096, 10_000, 2)));
+
+        session.assign_processes().await;
+
+        assert_eq!(session.jobs.len(), 1);
+        assert!(session.nodes[0].active_processes.is_empty());
+        assert_eq!(session.available_resources, session.total_resources);
+    }
+
+    #[tokio::test]
+    async fn test_gang_waits_behind_more_important_process() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(8_192, 20_000, 4));
+        let mut job = gang(1, 2, Resources::new(4_096, 1_000, 2));
+        job.processes.iter_mut().for_each(|p| p.priority = Priority::BestEffort);
+        session.submit_job(job);
+        session.processes.push(Process::with_priority(1, "Critical".to_string(), Resources::new(8_192, 1_000, 4), Priority::Critical));
+
+        session.assign_processes().await;
+
+        assert_eq!(session.nodes[0].active_processes[0].id, 1);
+        assert_eq!(session.jobs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_gang_follows_earliest_deadline_first() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(8_192, 20_000, 4));
+        session.clock = Clock::simulated(0);
+        session.queue_order = QueueOrder::EarliestDeadlineFirst;
+        let mut job = gang(1, 2, Resources::new(4_096, 1_000, 2));
+        job.processes.iter_mut().for_each(|p| p.deadline = Some(10_000));
+        session.submit_job(job);
+        let mut urgent = Process::new(1, "Urgent".to_string(), Resources::new(4_096, 1_000, 2));
+        urgent.deadline = Some(2_000);
+        session.processes.push(urgent);
+
+        session.assign_processes().await;
+
+        // The gang needs the whole node, the urgent process got there first
+        assert_eq!(session.nodes[0].active_processes.len(), 1);
+        assert_eq!(session.nodes[0].active_processes[0].id, 1);
+        assert_eq!(session.jobs.len(), 1);
+    }
+
+    fn timed_process(id: u32, deadline: u64, duration: u64) -> Process {
+        let mut process = Process::new(id, format!("Process {}", id), Resources::new(4_096, 1_000, 2));
+        process.deadline = Some(deadline);
+        process.estimated_duration = Some(duration);
+        process
+    }
+
+    #[tokio::test]
+    async fn test_earliest_deadline_first() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(4_096, 10_000, 2));
+        session.clock = Clock::simulated(0);
+        session.queue_order = QueueOrder::EarliestDeadlineFirst;
+        session.submit_process(Process::new(1, "No deadline".to_string(), Resources::new(4_096, 1_000, 2))).unwrap();
+        session.submit_process(timed_process(2, 5_000, 1_000)).unwrap();
+        session.submit_process(timed_process(3, 2_000, 1_000)).unwrap();
+
+        session.assign_processes().await;
+
+        assert_eq!(session.nodes[0].active_processes[0].id, 3);
+        assert_eq!(session.nodes[0].active_processes[0].started_at, Some(0));
+        let pending: Vec<u32> = session.processes.iter().map(|p| p.id).collect();
+        assert_eq!(pending, vec![2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_admission_control_rejects_unreachable_deadlines() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(4_096, 10_000, 2));
+        session.clock = Clock::simulated(0);
+        session.queue_order = QueueOrder::EarliestDeadlineFirst;
+        session.submit_process(timed_process(1, 1_000, 1_000)).unwrap();
+        session.assign_processes().await;
+
+        // The node is busy until 1000, so a 500ms process can't be done by 1200
+        assert_eq!(
+            session.submit_process(timed_process(2, 1_200, 500)),
+            Err(AdmissionError::DeadlineUnreachable { process_id: 2, deadline: 1_200, estimated_completion: 1_500 })
+        );
+        assert!(session.submit_process(timed_process(3, 1_500, 500)).is_ok());
+        // Queued behind process 3
+        assert_eq!(session.estimated_completion(&timed_process(4, 10_000, 500)), 2_000);
+    }
+
+    #[tokio::test]
+    async fn test_missed_deadlines_are_reported() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(4_096, 10_000, 2));
+        session.clock = Clock::simulated(0);
+        session.submit_process(timed_process(1, 1_000, 1_000)).unwrap();
+        session.submit_process(timed_process(2, 1_500, 100)).unwrap();
+        session.assign_processes().await;
+
+        // Process 1 runs late, process 2 is still waiting when its deadline passes
+        session.clock.advance(1_600);
+        session.complete_process(1);
+        assert_eq!(
+            session.missed_deadlines,
+            vec![
+                DeadlineMiss { process_id: 2, deadline: 1_500, detected_at: 1_600 },
+                DeadlineMiss { process_id: 1, deadline: 1_000, detected_at: 1_600 },
+            ]
+        );
+        assert!(session.check_deadlines().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_quota_is_enforced() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 100_000, 8));
+        session.quotas.insert("analytics".to_string(), Resources::new(4_096, 100_000, 8));
+        for id in 1..=3 {
+            let mut process = Process::new(id, format!("Query {}", id), Resources::new(2_048, 1_000, 1));
+            process.tenant = Some("analytics".to_string());
+            session.processes.push(process);
+        }
+
+        session.assign_processes().await;
+
+        // The cluster has room, but the tenant may only use 4 GB
+        assert_eq!(session.processes.len(), 1);
+        assert_eq!(session.tenant_usage()["analytics"], Resources::new(4_096, 2_000, 2));
+
+        // Once one of its processes finishes, the next one fits in the quota
+        session.complete_process(1);
+        session.assign_processes().await;
+        assert!(session.processes.is_empty());
+    }
+
+    #[test]
+    fn test_migration_to_forbidden_node_is_aborted() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.nodes[1].labels.insert("zone".to_string(), "b".to_string());
+        let mut process = Process::new(1, "Process 1".to_string(), Resources::new(1_024, 1_000, 1));
+        process.constraints = vec![Constraint::AntiAffinity(LabelSelector::new("zone", &["b"]))];
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process);
+
+        let action = Action::MigrateProcess { process_id: 1, target_node_id: 2 };
+        session.initiate_voting(2, action);
+
+        assert!(matches!(session.migrations[0].phase, MigrationPhase::Aborted { .. }));
+        assert!(matches!(session.nodes[1].action_outcomes[0].1, ActionOutcome::MigrationAborted { .. }));
+        assert_eq!(session.nodes[0].active_processes[0].id, 1);
+        // Nothing stays reserved on the target
+        assert_eq!(session.nodes[1].available_resources, session.nodes[1].total_resources);
+    }
+
+    #[test]
+    fn test_migration_needs_room_on_target() {
+        let mut session = Session::new(
+            vec![Node::with_resources(1, Resources::new(8_192, 10_000, 4)), Node::with_resources(2, Resources::new(1_024, 10_000, 4))],
+            vec![],
+            Resources::new(0, 0, 0),
+        );
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process);
+
+        session.start_migration(1, 1, 2);
+
+        assert!(matches!(session.migrations[0].phase, MigrationPhase::Aborted { .. }));
+        assert_eq!(session.nodes[1].available_resources, Resources::new(1_024, 10_000, 4));
+    }
+
+    #[test]
+    fn test_recovered_node_keeps_its_capacity() {
+        let mut session = Session::new(vec![Node::with_resources(1, Resources::new(2_048, 1_000, 1))], vec![], Resources::new(0, 0, 0));
+
+        session.handle_node_failure(1, "Crash".to_string());
+
+        assert_eq!(session.nodes[0].total_resources, Resources::new(2_048, 1_000, 1));
+        assert_eq!(session.total_resources, Resources::new(2_048, 1_000, 1));
+    }
+
+    #[test]
+    fn test_migration_to_inactive_node_is_aborted() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.nodes[0].active_processes.push(Process::new(1, "Process 1".to_string(), Resources::new(4_096, 1_000, 2)));
+        session.nodes[1].status = NodeStatus::Recovering;
+
+        session.start_migration(1, 1, 2);
+
+        assert!(session.migrations[0].is_finished());
+        assert!(matches!(session.nodes[0].action_outcomes[0].1, ActionOutcome::MigrationAborted { .. }));
+        assert_eq!(session.nodes[0].active_processes[0].id, 1);
+    }
+
+    #[test]
+    fn test_migration_of_unknown_process_is_aborted() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(0, 0, 0));
+
+        session.start_migration(1, 7, 2);
+
+        assert!(session.migrations[0].is_finished());
+        assert!(matches!(session.nodes[0].action_outcomes[0].1, ActionOutcome::MigrationAborted { .. }));
+    }
+
+    // One node with 6 GB used until t=1000, then a big, a long and a short process queued in that order
+    async fn blocked_queue(backfilling: bool) -> Session {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(8_192, 100_000, 8));
+        session.clock = Clock::simulated(0);
+        session.backfilling = backfilling;
+        let mut running = Process::new(1, "Running".to_string(), Resources::new(6_144, 1_000, 1));
+        running.estimated_duration = Some(1_000);
+        session.processes.push(running);
+        session.assign_processes().await;
+
+        let timed = |id: u32, ram: u64, duration: u64| {
+            let mut process = Process::new(id, format!("Process {}", id), Resources::new(ram, 1_000, 1));
+            process.estimated_duration = Some(duration);
+            process
+        };
+        session.processes.extend([timed(2, 8_192, 2_000), timed(3, 2_048, 5_000), timed(4, 2_048, 500)]);
+        session.assign_processes().await;
+        session
+    }
+
+    fn running_ids(session: &Session) -> Vec<u32> {
+        session.nodes.iter().flat_map(|n| n.active_processes.iter().map(|p| p.id)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_without_backfilling_long_process_delays_head() {
+        let session = blocked_queue(false).await;
+
+        assert_eq!(running_ids(&session), vec![1, 3]);
+        assert_eq!(session.reservation, None);
+    }
+
+    #[tokio::test]
+    async fn test_backfilling_only_runs_processes_ending_before_reservation() {
+        let mut session = blocked_queue(true).await;
+
+        // Only the short process fits in the gap before process 2 can start
+        assert_eq!(running_ids(&session), vec![1, 4]);
+        let reservation = session.reservation.clone().unwrap();
+        assert_eq!((reservation.process_id, reservation.node_id, reservation.start_time), (2, 1, 1_000));
+
+        session.clock.advance(1_000);
+        session.complete_process(1);
+        session.complete_process(4);
+        session.assign_processes().await;
+
+        // The head starts on time, the long process keeps waiting behind it
+        assert_eq!(running_ids(&session), vec![2]);
+        assert_eq!(session.processes.iter().map(|p| p.id).collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_backfilling_uses_capacity_left_by_reservation() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(8_192, 100_000, 8));
+        session.clock = Clock::simulated(0);
+        session.backfilling = true;
+        let mut running = Process::new(1, "Running".to_string(), Resources::new(4_096, 1_000, 1));
+        running.estimated_duration = Some(1_000);
+        session.processes.push(running);
+        session.assign_processes().await;
+
+        // Process 2 needs 6 GB at t=1000, 2 GB stay free for a process without estimate
+        session.processes.push(Process::new(2, "Big".to_string(), Resources::new(6_144, 1_000, 1)));
+        session.processes.push(Process::new(3, "Small".to_string(), Resources::new(2_048, 1_000, 1)));
+        session.processes.push(Process::new(4, "Small".to_string(), Resources::new(2_048, 1_000, 1)));
+        session.assign_processes().await;
+
+        assert_eq!(running_ids(&session), vec![1, 3]);
+        assert_eq!(session.reservation.unwrap().extra.ram, 0);
+    }
+
+    // Node 1 runs a long process with three more queued behind it, nodes 2 and 3 are idle
+    fn unbalanced_session() -> Session {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(12_288, 30_000, 6));
+        for id in 1..=4 {
+            session.dispatch_process(1, Process::new(id, format!("Process {}", id), Resources::new(4_096, 10_000, 2)));
+        }
+        session.start_queued_processes();
+        session
+    }
+
+    #[test]
+    fn test_local_queue_starts_in_order() {
+        let session = unbalanced_session();
+
+        assert_eq!(running_ids(&session), vec![1]);
+        let queued: Vec<u32> = session.nodes[0].local_queue.iter().map(|p| p.id).collect();
+        assert_eq!(queued, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_idle_nodes_steal_queued_processes() {
+        let mut session = unbalanced_session();
+        assert!(session.steal_work().is_empty());
+
+        session.work_stealing = Some(WorkStealing::new(42));
+        let steals = session.steal_work();
+
+        // Both idle nodes take one process from the back of node 1's queue
+        assert_eq!(steals.iter().map(|s| (s.thief_id, s.victim_id, s.process_id)).collect::<Vec<_>>(), vec![(2, 1, 4), (3, 1, 3)]);
+        assert_eq!(session.nodes[0].local_queue.len(), 1);
+        assert_eq!(running_ids(&session), vec![1, 4, 3]);
+
+        // Nothing left that the busy nodes can run
+        assert!(session.steal_work().is_empty());
+        assert_eq!(session.work_stealing.unwrap().steals.len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_blocked_process_waits_on_a_node_until_stolen() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(8_192, 20_000, 4));
+        session.clock = Clock::simulated(0);
+        let mut stealing = WorkStealing::new(42);
+        stealing.interval = 10;
+        session.work_stealing = Some(stealing);
+        for id in 1..=3 {
+            session.processes.push(Process::new(id, format!("Process {}", id), Resources::new(4_096, 1_000, 2)));
+        }
+        session.assign_processes().await;
+
+        // Process 3 fits nowhere yet, it waits on a node rather than in the cluster queue
+        assert!(session.processes.is_empty());
+        let queued_on = session.nodes.iter().position(|n| !n.local_queue.is_empty()).unwrap();
+        assert_eq!(session.nodes[queued_on].local_queue[0].id, 3);
+        assert_eq!(session.nodes[queued_on].local_queue[0].state, ProcessState::Scheduled);
+
+        // The other node frees up and steals it in the next background round
+        let other = 1 - queued_on;
+        let finished = session.nodes[other].active_processes[0].id;
+        assert!(session.complete_process(finished));
+        let session = Arc::new(Mutex::new(session));
+        let handle = spawn_work_stealer(Arc::clone(&session));
+        sleep(Duration::from_millis(15)).await;
+        handle.abort();
+
+        let session = session.lock().await;
+        assert_eq!(session.clock.now(), 10);
+        assert_eq!(session.work_stealing.as_ref().unwrap().steals.len(), 1);
+        assert!(session.nodes[other].active_processes.iter().any(|p| p.id == 3));
+        assert!(session.nodes[queued_on].local_queue.is_empty());
+    }
+
+    #[test]
+    fn test_removed_node_requeues_local_queue() {
+        let mut session = unbalanced_session();
+        session.remove_node(1);
+
+        let pending: Vec<u32> = session.processes.iter().map(|p| p.id).collect();
+        assert_eq!(pending, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_rebalancer_moves_processes_off_busiest_node() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.clock = Clock::simulated(0);
+        for id in 1..=3 {
+            let process = Process::new(id, format!("Process {}", id), Resources::new(2_048, 1_000, 1));
+            session.allocate_resources(1, &process.needed_resources);
+            session.nodes[0].active_processes.push(process);
+        }
+        assert!(session.rebalance().is_empty());
+
+        session.rebalancer = Some(Rebalancer::new(RebalanceConfig { budget: 1, threshold: 0.1, ..RebalanceConfig::default() }));
+        assert_eq!(session.rebalance(), vec![1]);
+        assert_eq!(
+            session.committed_actions,
+            vec![Action::RedistributeProcess { process_id: 1, target_node_id: Some(2) }]
+        );
+        assert_eq!(running_ids(&session), vec![2, 3, 1]);
+
+        // Not due yet, then no move would lower the busiest load
+        assert!(session.rebalance().is_empty());
+        session.clock.advance(10_000);
+        assert!(session.rebalance().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_background_rebalancer() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.clock = Clock::simulated(0);
+        for id in 1..=4 {
+            let process = Process::new(id, format!("Process {}", id), Resources::new(2_048, 1_000, 1));
+            session.allocate_resources(1, &process.needed_resources);
+            session.nodes[0].active_processes.push(process);
+        }
+        session.rebalancer = Some(Rebalancer::new(RebalanceConfig { interval: 10, ..RebalanceConfig::default() }));
+        let session = Arc::new(Mutex::new(session));
+
+        // Tokio's clock is paused, the three rounds run in order without waiting
+        let handle = spawn_rebalancer(Arc::clone(&session));
+        sleep(Duration::from_millis(35)).await;
+        handle.abort();
+
+        let session = session.lock().await;
+        assert_eq!(session.clock.now(), 30);
+        assert_eq!(session.nodes[0].active_processes.len(), 2);
+        assert_eq!(session.nodes[1].active_processes.len(), 2);
+    }
+
+    fn autoscaled_session(config: AutoscalerConfig) -> Session {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(8_192, 100_000, 4));
+        session.clock = Clock::simulated(0);
+        let provider = StaticProvider::new(Resources::new(8_192, 100_000, 4));
+        session.autoscaler = Some(Autoscaler::new(config, Box::new(provider)));
+        session
+    }
+
+    #[tokio::test]
+    async fn test_autoscaler_adds_nodes_for_sustained_demand() {
+        let config = AutoscalerConfig { max_nodes: 2, scale_up_after: 1_000, cooldown: 2_000, ..AutoscalerConfig::default() };
+        let mut session = autoscaled_session(config);
+        for id in 1..=3 {
+            session.processes.push(Process::new(id, format!("Process {}", id), Resources::new(8_192, 1_000, 1)));
+        }
+        session.assign_processes().await;
+
+        // Demand has to last before scaling
+        assert!(session.autoscale().is_empty());
+        session.clock.advance(1_000);
+        assert_eq!(session.autoscale(), vec![ScalingEvent::ScaledUp { node_id: 2, at: 1_000 }]);
+        session.assign_processes().await;
+        assert_eq!(session.processes.len(), 1);
+
+        // Still pending, but the cluster is at its maximum size
+        session.clock.advance(5_000);
+        assert!(session.autoscale().is_empty());
+        assert_eq!(session.total_nodes(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_autoscaler_drains_idle_nodes() {
+        let config = AutoscalerConfig { scale_down_after: 1_000, cooldown: 0, ..AutoscalerConfig::default() };
+        let mut session = autoscaled_session(config);
+        for id in 2..=4 {
+            session.add_node(Node::with_resources(id, Resources::new(8_192, 100_000, 4)));
+        }
+        // As if the autoscaler had added nodes 2 and 3, node 4 was added by hand
+        session.autoscaler.as_mut().unwrap().provisioned = vec![2, 3];
+        session.processes.push(Process::new(1, "Process 1".to_string(), Resources::new(1_024, 1_000, 1)));
+        session.assign_processes().await;
+
+        assert!(session.autoscale().is_empty());
+        session.clock.advance(1_000);
+        let events = session.autoscale();
+        assert_eq!(events, vec![ScalingEvent::ScalingDown { node_id: 2, at: 1_000 }]);
+
+        session.advance_drains();
+        assert_eq!(session.autoscale(), vec![
+            ScalingEvent::Released { node_id: 2, at: 1_000 },
+            ScalingEvent::ScalingDown { node_id: 3, at: 1_000 },
+        ]);
+        session.advance_drains();
+
+        // Only the nodes the autoscaler added are removed, whether idle or not
+        session.autoscale();
+        assert_eq!(session.nodes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![1, 4]);
+        assert!(session.autoscaler.as_ref().unwrap().provisioned.is_empty());
+        session.clock.advance(60_000);
+        session.complete_process(1);
+        session.autoscale();
+        session.clock.advance(1_000);
+        assert!(session.autoscale().is_empty());
+        assert_eq!(session.total_nodes(), 2);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::node::Node;
-    use crate::process::Process;
-    use crate::resource::Resources;
-    use crate::message::{Action, Vote};
-    use crate::message::Request;
+    #[tokio::test]
+    async fn test_autoscaler_ignores_processes_blocked_by_quota_or_constraints() {
+        let config = AutoscalerConfig { scale_up_after: 1_000, ..AutoscalerConfig::default() };
+        let mut session = autoscaled_session(config);
+        session.quotas.insert("a".to_string(), Resources::new(1_024, 1_000, 1));
+        let mut over_quota = Process::new(1, "Over quota".to_string(), Resources::new(2_048, 1_000, 1));
+        over_quota.tenant = Some("a".to_string());
+        let mut constrained = Process::new(2, "Constrained".to_string(), Resources::new(1_024, 1_000, 1));
+        constrained.constraints.push(Constraint::RequiredAffinity(LabelSelector::new("gpu", &["true"])));
+        session.processes.extend([over_quota, constrained]);
+        session.assign_processes().await;
+        assert_eq!(session.processes.len(), 2);
+
+        session.autoscale();
+        session.clock.advance(1_000);
+        assert!(session.autoscale().is_empty());
+        assert_eq!(session.total_nodes(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_process_lifecycle() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(4_096, 10_000, 2));
+        session.clock = Clock::simulated(0);
+        for id in 1..=3 {
+            session.processes.push(Process::new(id, format!("Process {}", id), Resources::new(2_048, 1_000, 1)));
+        }
+        session.dispatch_process(1, Process::new(4, "Local".to_string(), Resources::new(2_048, 1_000, 1)));
+        session.clock.advance(100);
+        session.assign_processes().await;
+
+        let ids = |session: &Session, state| -> Vec<u32> { session.processes_in_state(state).iter().map(|p| p.id).collect() };
+        assert_eq!(ids(&session, ProcessState::Running), vec![1, 2]);
+        assert_eq!(ids(&session, ProcessState::Pending), vec![3]);
+        assert_eq!(ids(&session, ProcessState::Scheduled), vec![4]);
+
+        session.clock.advance(100);
+        assert!(session.complete_process(1));
+        assert!(session.fail_process(2, "Crash".to_string()));
+        assert!(session.cancel_process(3));
+        assert!(session.cancel_process(4));
+        // Finished processes can't finish again
+        assert!(!session.complete_process(1));
+        assert!(!session.cancel_process(3));
+
+        assert_eq!(session.process_state(1), Some(ProcessState::Succeeded));
+        assert_eq!(session.process_state(2), Some(ProcessState::Failed));
+        assert_eq!(ids(&session, ProcessState::Cancelled), vec![3, 4]);
+        let first = &session.finished_processes[0];
+        assert_eq!(
+            first.transitions,
+            vec![(ProcessState::Scheduled, 100), (ProcessState::Running, 100), (ProcessState::Succeeded, 200)]
+        );
+        assert_eq!(session.nodes[0].available_resources, Resources::new(4_096, 10_000, 2));
+    }
+
+    // Node 1 runs a process and fails, node 2 can't take any work
+    fn session_without_spare_capacity() -> Session {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(8_192, 100_000, 4));
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 10_000, 2));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process);
+        session.nodes[1].status = NodeStatus::Halted;
+        session
+    }
 
     #[test]
-    fn test_session_new() {
-        let nodes = vec![Node::new(1), Node::new(2)];
-        let processes = vec![
-            Process::new(1, "Process 1".to_string(), Resources::new(1024, 100_000, 2)),
-            Process::new(2, "Process 2".to_string(), Resources::new(2048, 200_000, 4)),
-        ];
-        let total_resources = Resources::new(16_384, 1_000_000, 8);
-    
-        let session = Session::new(nodes.clone(), processes.clone(), total_resources.clone());
-    
-        assert_eq!(session.nodes, nodes);
-        assert_eq!(session.processes, processes);
-        assert_eq!(session.total_resources, total_resources);
-        assert_eq!(session.available_resources, total_resources);
-        assert!(session.pending_votes.is_empty());
-        assert!(session.request_queue.is_empty());
-        assert!(session.deferred_replies.is_empty());
-        assert!(session.replies_received.is_empty());
+    fn test_node_failure_reschedules_orphans() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 10_000, 2));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process.clone());
+
+        session.handle_node_failure(1, "Crash".to_string());
+
+        let node2 = session.nodes.iter().find(|n| n.id == 2).unwrap();
+        let running: Vec<u32> = node2.active_processes.iter().map(|p| p.id).collect();
+        assert_eq!(running, vec![process.id]);
+        assert!(session.processes.is_empty());
+        assert_eq!(session.available_resources, Resources::new(12_288, 990_000, 6));
     }
 
     #[test]
-    fn test_add_and_remove_node() {
-        let mut session = Session::new(vec![], vec![], Resources::new(0, 0, 0));
-        let node1 = Node::new(1);
-        let node2 = Node::new(2);
-    
-        session.add_node(node1.clone());
-        assert_eq!(session.nodes.len(), 1);
-        assert_eq!(session.nodes[0], node1);
-    
-        session.add_node(node2.clone());
-        assert_eq!(session.nodes.len(), 2);
-        assert_eq!(session.nodes[1], node2);
-    
-        session.remove_node(1);
-        assert_eq!(session.nodes.len(), 1);
-        assert_eq!(session.nodes[0], node2);
+    fn test_orphan_policy_queue() {
+        let mut session = session_without_spare_capacity();
+        session.orphan_policy = OrphanPolicy::Queue;
+
+        session.handle_node_failure(1, "Crash".to_string());
+        assert_eq!(session.processes.len(), 1);
+        assert!(session.shed_processes.is_empty());
     }
 
     #[test]
-    fn test_total_nodes() {
-        let mut session = Session::new(vec![], vec![], Resources::new(0, 0, 0));
-        assert_eq!(session.total_nodes(), 0);
-    
-        session.add_node(Node::new(1));
-        session.add_node(Node::new(2));
-        assert_eq!(session.total_nodes(), 2);
-    
-        session.remove_node(1);
-        assert_eq!(session.total_nodes(), 1);
+    fn test_orphan_policy_shed() {
+        let mut session = session_without_spare_capacity();
+        session.orphan_policy = OrphanPolicy::ShedLowestPriority;
+
+        session.handle_node_failure(1, "Crash".to_string());
+        assert!(session.processes.is_empty());
+        assert_eq!(session.shed_processes[0].id, 1);
     }
 
     #[test]
-    fn test_allocate_resources_success() {
-        let total_resources = Resources::new(16_384, 1_000_000, 8);
-        let mut session = Session::new(vec![], vec![], total_resources.clone());
-    
-        let needed_resources = Resources::new(4_096, 200_000, 2);
-    
-        let result = session.allocate_resources(&needed_resources);
-        assert!(result);
+    fn test_shed_orphan_is_marked_failed() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(8_192, 100_000, 4));
+        session.clock = Clock::simulated(0);
+        session.orphan_policy = OrphanPolicy::ShedLowestPriority;
+        assert!(session.bind_process(1, &Process::new(1, "Process 1".to_string(), Resources::new(4_096, 10_000, 2))));
+        session.nodes[1].status = NodeStatus::Halted;
+
+        session.clock.advance(100);
+        session.handle_node_failure(1, "Crash".to_string());
+
+        let shed = &session.shed_processes[0];
+        assert_eq!(shed.state, ProcessState::Failed);
         assert_eq!(
-            session.available_resources,
-            Resources::new(12_288, 800_000, 6)
+            shed.transitions,
+            vec![
+                (ProcessState::Scheduled, 0),
+                (ProcessState::Running, 0),
+                (ProcessState::Pending, 100),
+                (ProcessState::Failed, 100),
+            ]
         );
     }
-    
+
     #[test]
-    fn test_allocate_resources_failure() {
-        let total_resources = Resources::new(16_384, 1_000_000, 8);
-        let mut session = Session::new(vec![], vec![], total_resources.clone());
-    
-        let needed_resources = Resources::new(32_768, 2_000_000, 16);
-    
-        let result = session.allocate_resources(&needed_resources);
-        assert!(!result);
-        assert_eq!(session.available_resources, total_resources);
+    fn test_shed_lowest_priority_orphans_first() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(8_192, 100_000, 4));
+        session.orphan_policy = OrphanPolicy::ShedLowestPriority;
+        for (id, priority) in [(1, Priority::BestEffort), (2, Priority::High)] {
+            let process = Process::with_priority(id, format!("Process {}", id), Resources::new(2_048, 1_000, 1), priority);
+            session.allocate_resources(1, &process.needed_resources);
+            session.nodes[0].active_processes.push(process);
+        }
+        // Node 2 only has room for one of them
+        session.allocate_resources(2, &Resources::new(2_048, 0, 0));
+
+        session.handle_node_failure(1, "Crash".to_string());
+
+        assert_eq!(session.nodes[0].active_processes[0].id, 2);
+        assert_eq!(session.shed_processes[0].id, 1);
     }
 
     #[test]
-    fn test_deallocate_resources() {
-        let total_resources = Resources::new(16_384, 1_000_000, 8);
-        let mut session = Session::new(vec![], vec![], total_resources.clone());
-    
-        let needed_resources = Resources::new(4_096, 200_000, 2);
-        session.allocate_resources(&needed_resources);
-    
-        session.deallocate_resources(&needed_resources);
-        assert_eq!(session.available_resources, total_resources);
+    fn test_orphan_policy_scale_out() {
+        let mut session = session_without_spare_capacity();
+        session.orphan_policy = OrphanPolicy::ScaleOut { node_resources: Resources::new(4_096, 50_000, 2) };
+
+        session.handle_node_failure(1, "Crash".to_string());
+
+        let new_node = session.nodes.iter().find(|n| n.id == 3).unwrap();
+        assert_eq!(new_node.active_processes[0].id, 1);
+        assert!(session.processes.is_empty());
+        assert_eq!(session.total_resources, Resources::new(12_288, 150_000, 6));
+        assert_eq!(session.total_resources, session.nodes.iter().map(|n| &n.total_resources).sum());
+    }
+
+    #[test]
+    fn test_scale_out_skips_nodes_too_small() {
+        let mut session = session_without_spare_capacity();
+        session.orphan_policy = OrphanPolicy::ScaleOut { node_resources: Resources::new(2_048, 50_000, 2) };
+
+        session.handle_node_failure(1, "Crash".to_string());
+
+        assert!(!session.nodes.iter().any(|n| n.id == 3));
+        assert_eq!(session.processes[0].id, 1);
     }
 
     #[tokio::test]
-    async fn test_assign_processes() {
-        let node1 = Node::new(1);
-        let node2 = Node::new(2);
-        let mut session = Session::new(
-            vec![node1.clone(), node2.clone()],
-            vec![],
-            Resources::new(16_384, 1_000_000, 8),
-        );
-    
-        let process1 = Process::new(1, "Process 1".to_string(), Resources::new(4_096, 200_000, 2));
-        let process2 = Process::new(2, "Process 2".to_string(), Resources::new(4_096, 200_000, 2));
-        session.processes.push(process1.clone());
-        session.processes.push(process2.clone());
-    
+    async fn test_drain_node_migrates_processes() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.clock = Clock::simulated(0);
+        for id in 1..=2 {
+            let process = Process::new(id, format!("Process {}", id), Resources::new(1_024, 1_000, 1));
+            session.allocate_resources(1, &process.needed_resources);
+            session.nodes[0].active_processes.push(process);
+        }
+        session.request_resource(1).await;
+
+        let action = Action::DrainNode { node_id: 1, timeout: 10_000 };
+        session.initiate_voting(2, action.clone());
+        assert_eq!(session.nodes[0].status, NodeStatus::Draining);
+
+        // Draining nodes don't receive new work
+        session.processes.push(Process::new(3, "Process 3".to_string(), Resources::new(1_024, 1_000, 1)));
         session.assign_processes().await;
-    
-        // Check that processes have been assigned
-        let node1_processes = session.nodes.iter().find(|n| n.id == 1).unwrap().active_processes.clone();
-        let node2_processes = session.nodes.iter().find(|n| n.id == 2).unwrap().active_processes.clone();
-    
-        assert_eq!(node1_processes.len() + node2_processes.len(), 2);
-    
-        // Check that resources have been allocated
+        assert!(session.nodes[0].active_processes.iter().all(|p| p.id != 3));
+
+        for _ in 0..10 {
+            session.clock.advance(100);
+            session.advance_migrations();
+            session.advance_drains();
+        }
+
+        assert!(!session.nodes.iter().any(|n| n.id == 1));
+        assert!(session.drains.is_empty());
+        // Its lock request is gone
+        assert!(session.request_queue.iter().all(|r| r.from_node_id != 1));
+        let running: usize = session.nodes.iter().map(|n| n.active_processes.len()).sum();
+        assert_eq!(running, 3);
+        // The proposer gets the outcome of the drain and of each migration
+        let outcomes = &session.nodes[0].action_outcomes;
+        assert_eq!(outcomes.len(), 3);
         assert_eq!(
-            session.available_resources,
-            Resources::new(8_192, 600_000, 4)
+            outcomes[0],
+            (action, ActionOutcome::Drained { node_id: 1, migrated: vec![1, 2], requeued: vec![] })
         );
+        assert!(outcomes[1..].iter().all(|(_, outcome)| matches!(outcome, ActionOutcome::Migrated { from_node_id: 1, .. })));
     }
 
     #[tokio::test]
-    async fn test_request_resource_and_can_access_resource() {
-        let node1 = Node::new(1);
-        let node2 = Node::new(2);
-        let node3 = Node::new(3);
-        let mut session = Session::new(
-            vec![node1.clone(), node2.clone(), node3.clone()],
-            vec![],
-            Resources::new(16_384, 1_000_000, 8),
+    async fn test_drain_requeues_local_queue_and_reports_to_proposer() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2), Node::new(3)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.clock = Clock::simulated(0);
+        session.migration_config.max_precopy_rounds = 1;
+        assert!(session.bind_process(1, &Process::new(1, "Process 1".to_string(), Resources::new(1_024, 1_000, 1))));
+        session.dispatch_process(1, Process::new(2, "Process 2".to_string(), Resources::new(1_024, 1_000, 1)));
+
+        // The node can't drain itself
+        session.initiate_voting(1, Action::DrainNode { node_id: 1, timeout: 10_000 });
+        assert!(session.drains.is_empty());
+
+        let action = Action::DrainNode { node_id: 1, timeout: 10_000 };
+        session.initiate_voting(2, action.clone());
+        assert!(session.nodes[0].local_queue.is_empty());
+        assert_eq!(session.processes[0].id, 2);
+        assert_eq!(session.processes[0].state, ProcessState::Pending);
+
+        for _ in 0..5 {
+            session.advance_migrations();
+            session.advance_drains();
+        }
+        assert!(!session.nodes.iter().any(|n| n.id == 1));
+        // The migration outcome reached the proposer, not the removed node
+        let proposer = session.nodes.iter().find(|n| n.id == 2).unwrap();
+        assert!(proposer
+            .action_outcomes
+            .iter()
+            .any(|(_, outcome)| matches!(outcome, ActionOutcome::Migrated { process_id: 1, .. })));
+        assert!(proposer.action_outcomes.iter().any(|(a, _)| *a == action));
+    }
+
+    #[test]
+    fn test_drain_deadline_requeues_remaining_processes() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(16_384, 1_000_000, 8));
+        session.clock = Clock::simulated(0);
+        let process = Process::new(1, "Process 1".to_string(), Resources::new(1_024, 1_000, 1));
+        session.allocate_resources(1, &process.needed_resources);
+        session.nodes[0].active_processes.push(process);
+        // No other node can take it during the drain
+        session.nodes[1].status = NodeStatus::Recovering;
+
+        session.start_drain(2, 1, 1_000);
+        session.advance_drains();
+        assert!(session.nodes.iter().any(|n| n.id == 1));
+
+        session.nodes[1].status = NodeStatus::Active;
+        session.clock.advance(1_000);
+        session.advance_drains();
+
+        assert!(!session.nodes.iter().any(|n| n.id == 1));
+        assert_eq!(session.nodes[0].active_processes[0].id, 1);
+        assert_eq!(
+            session.nodes[0].action_outcomes[0].1,
+            ActionOutcome::Drained { node_id: 1, migrated: vec![], requeued: vec![1] }
         );
-    
-        // Node 1 requests resource
-        session.request_resource(1).await;
-        // Since no other nodes have requested, Node 1 should have access
-        assert!(session.can_access_resource(1));
-    
-        // Node 2 requests resource
-        session.request_resource(2).await;
-        // Node 2 should have access
-        assert!(session.can_access_resource(2));
-    
-        // Node 1 releases resource
-        session.release_resource(1);
-    
-        // Now Node 2 should have access
-        assert!(session.can_access_resource(2));
     }
 
     #[test]
-    fn test_handle_request_and_should_reply_immediately() {
-        let node1 = Node::new(1);
-        let node2 = Node::new(2);
-        let mut session = Session::new(vec![node1.clone(), node2.clone()], vec![], Resources::new(0, 0, 0));
-    
-        // Simulate Node 1's request
-        let request1 = Request {
-            from_node_id: 1,
-            timestamp: 100,
-        };
-        session.request_queue.push_back(request1.clone());
-    
-        // Node 2 receives Node 1's request
-        session.handle_request(2, request1.clone());
-    
-        // Since Node 2 hasn't requested, it should send a reply immediately
-        assert_eq!(session.replies_received.get(&1).unwrap().len(), 1);
-        assert_eq!(session.replies_received.get(&1).unwrap()[0], 2);
-    
-        // Now, Node 2 requests the resource with a higher timestamp
-        let request2 = Request {
-            from_node_id: 2,
-            timestamp: 200,
-        };
-        session.request_queue.push_back(request2.clone());
-    
-        // Node 1 handles Node 2's request
-        session.handle_request(1, request2.clone());
-    
-        // Node 1 should defer the reply since its request has a lower timestamp
-        assert!(session.deferred_replies.get(&1).unwrap().len() == 1);
+    fn test_join_admits_node_with_cluster_state() {
+        let mut session = converged_session(3);
+        let action = Action::ProcessFailure { node_id: 9, reason: "Timeout".to_string() };
+        session.initiate_voting(1, action.clone());
+
+        assert_eq!(session.request_join(Node::new(4)), Ok(JoinStatus::Admitted));
+
+        let node = session.nodes.iter().find(|n| n.id == 4).unwrap();
+        assert_eq!(node.membership.live_members(), vec![1, 2, 3]);
+        assert!(node.known_actions.contains_key(&action));
+        assert!(node.caught_up);
+        for member in session.nodes.iter().filter(|n| n.id != 4) {
+            assert_eq!(member.membership.status_of(4), Some(MemberStatus::Alive));
+        }
     }
 
     #[test]
-    fn test_release_resource_and_deferred_replies() {
-        let node1 = Node::new(1);
-        let node2 = Node::new(2);
-        let mut session = Session::new(vec![node1.clone(), node2.clone()], vec![], Resources::new(0, 0, 0));
-    
-        // Node 1 requests resource
-        let request1 = Request {
-            from_node_id: 1,
-            timestamp: 100,
-        };
-        session.request_queue.push_back(request1.clone());
-    
-        // Node 2 handles Node 1's request and replies immediately
-        session.handle_request(2, request1.clone());
-    
-        // Node 2 requests resource
-        let request2 = Request {
-            from_node_id: 2,
-            timestamp: 200,
-        };
-        session.request_queue.push_back(request2.clone());
-    
-        // Node 1 handles Node 2's request and defers reply
-        session.handle_request(1, request2.clone());
-    
-        // Node 1 releases resource
-        session.release_resource(1);
-    
-        // Node 1 should send the deferred reply to Node 2
-        assert_eq!(session.replies_received.get(&2).unwrap().len(), 1);
-        assert_eq!(session.replies_received.get(&2).unwrap()[0], 1);
+    fn test_join_rejects_duplicate_and_conflicting_ids() {
+        let mut session = Session::new(vec![Node::new(1), Node::new(2)], vec![], Resources::new(0, 0, 0));
+        assert_eq!(session.request_join(Node::new(2)), Err(JoinError::DuplicateId { node_id: 2 }));
+
+        session.handle_node_failure(1, "Crash".to_string());
+        session.remove_node(1);
+        assert!(matches!(session.request_join(Node::new(1)), Err(JoinError::ConflictingId { node_id: 1, .. })));
+        assert_eq!(session.total_nodes(), 1);
+    }
+
+    #[test]
+    fn test_join_rejected_by_members() {
+        let mut session = converged_session(3);
+        // Node 4 is still alive in the members' view, its id can't be reused
+        session.nodes.iter_mut().for_each(|n| n.membership.add_seed(4, 0));
+
+        assert_eq!(session.request_join(Node::new(4)), Err(JoinError::Rejected { node_id: 4 }));
+        assert!(session.pending_joins.is_empty());
+    }
+
+    #[test]
+    fn test_join_without_quorum() {
+        let mut session = converged_session(3);
+        session.network.partition(vec![vec![1], vec![2], vec![3]]);
+
+        assert_eq!(session.request_join(Node::new(4)), Err(JoinError::NoQuorum { node_id: 4 }));
+        assert!(session.pending_joins.is_empty());
+        assert_eq!(session.total_nodes(), 3);
+    }
+
+    #[test]
+    fn test_join_empty_cluster() {
+        let mut session = Session::new(vec![], vec![], Resources::new(0, 0, 0));
+
+        assert_eq!(session.request_join(Node::new(1)), Ok(JoinStatus::Admitted));
+        assert_eq!(session.total_nodes(), 1);
     }
 
     // TODO: Add tests for voting and consensus
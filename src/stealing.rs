@@ -0,0 +1,94 @@
+use crate::session::Session;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+
+// A queued process taken by an idle node from another node's local queue
+#[derive(Clone, Debug, PartialEq)]
+pub struct Steal {
+    pub thief_id: u32,
+    pub victim_id: u32,
+    pub process_id: u32,
+    pub at: u64,
+}
+
+// Work stealing between the nodes' local queues.
+// Victims are picked at random from a seeded generator so runs are reproducible.
+#[derive(Clone, Debug)]
+pub struct WorkStealing {
+    pub seed: u64,
+    rng: SmallRng,
+    // Time between two rounds of `spawn_work_stealer`
+    pub interval: u64,
+    // Queued processes a node needs to be stolen from
+    pub min_queue_length: usize,
+    // Every steal so far
+    pub steals: Vec<Steal>,
+}
+
+impl WorkStealing {
+    pub fn new(seed: u64) -> Self {
+        WorkStealing {
+            seed,
+            rng: SmallRng::seed_from_u64(seed),
+            interval: 1_000,
+            min_queue_length: 1,
+            steals: Vec::new(),
+        }
+    }
+
+    // Take a random victim out of the candidates
+    pub fn pick_victim(&mut self, candidates: &mut Vec<u32>) -> Option<u32> {
+        if candidates.is_empty() {
+            return None;
+        }
+        let index = self.rng.gen_range(0..candidates.len());
+        Some(candidates.swap_remove(index))
+    }
+}
+
+// Every `interval` of the session's work stealing, start what fits from the local queues and let the idle
+// nodes steal, moving a simulated clock forward by the same amount. Runs until the task is aborted or the
+// session has no work stealing.
+pub fn spawn_work_stealer(session: Arc<Mutex<Session>>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let interval = match &session.lock().await.work_stealing {
+                Some(stealing) => stealing.interval,
+                None => return,
+            };
+            sleep(Duration::from_millis(interval)).await;
+            let mut session = session.lock().await;
+            session.clock.advance(interval);
+            session.start_queued_processes();
+            session.steal_work();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_victim_is_reproducible() {
+        let picks = |seed: u64| {
+            let mut stealing = WorkStealing::new(seed);
+            let mut candidates = vec![1, 2, 3, 4, 5];
+            let mut picks = Vec::new();
+            while let Some(victim) = stealing.pick_victim(&mut candidates) {
+                picks.push(victim);
+            }
+            picks
+        };
+
+        let first = picks(7);
+        assert_eq!(first, picks(7));
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![1, 2, 3, 4, 5]);
+    }
+}
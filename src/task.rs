@@ -1,13 +1,314 @@
 use crate::resource::Resources;
+use std::collections::HashMap;
+use std::fmt;
 
+// Unit of work of a workflow, runs as a process once its dependencies succeeded
+#[derive(Clone, Debug, PartialEq)]
 pub struct Task {
     pub id: u32,
     pub description: String,
     pub resource_requirements: Resources,
+    // Tasks that must succeed before this one starts
+    pub dependencies: Vec<u32>,
+    // Times the task is run again after failing
+    pub max_retries: u32,
 }
 
 impl Task {
     pub fn new(id: u32, description: String, resource_requirements: Resources) -> Self {
-        Task { id, description, resource_requirements }
+        Task::with_dependencies(id, description, resource_requirements, Vec::new())
     }
-}
\ No newline at end of file
+
+    pub fn with_dependencies(id: u32, description: String, resource_requirements: Resources, dependencies: Vec<u32>) -> Self {
+        Task { id, description, resource_requirements, dependencies, max_retries: 0 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaskStatus {
+    // Waiting for its dependencies, or for a retry
+    Pending,
+    Running { process_id: u32 },
+    Succeeded,
+    Failed { reason: String },
+    // Never run because a task it depends on failed
+    Skipped { failed_dependency: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskState {
+    pub status: TaskStatus,
+    pub attempts: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorkflowStatus {
+    Running,
+    Succeeded,
+    // Finished with at least one failed task
+    Failed,
+}
+
+// Why a workflow was refused
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorkflowError {
+    DuplicateTask { task_id: u32 },
+    UnknownDependency { task_id: u32, dependency: u32 },
+    // The dependencies of these tasks form a cycle
+    Cycle { task_ids: Vec<u32> },
+}
+
+impl fmt::Display for WorkflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkflowError::DuplicateTask { task_id } => write!(f, "Task {} is defined twice", task_id),
+            WorkflowError::UnknownDependency { task_id, dependency } => {
+                write!(f, "Task {} depends on unknown task {}", task_id, dependency)
+            }
+            WorkflowError::Cycle { task_ids } => write!(f, "Tasks {:?} depend on each other", task_ids),
+        }
+    }
+}
+
+impl std::error::Error for WorkflowError {}
+
+// Final (or current) state of a workflow
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkflowReport {
+    pub workflow_id: u32,
+    pub status: WorkflowStatus,
+    pub succeeded: Vec<u32>,
+    pub failed: Vec<(u32, String)>,
+    pub skipped: Vec<u32>,
+    // Runs of every task, retries included
+    pub attempts: Vec<(u32, u32)>,
+}
+
+// DAG of tasks
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workflow {
+    pub id: u32,
+    pub tasks: Vec<Task>,
+    pub states: HashMap<u32, TaskState>,
+}
+
+impl Workflow {
+    pub fn new(id: u32, tasks: Vec<Task>) -> Result<Self, WorkflowError> {
+        let mut states = HashMap::new();
+        for task in tasks.iter() {
+            let state = TaskState { status: TaskStatus::Pending, attempts: 0 };
+            if states.insert(task.id, state).is_some() {
+                return Err(WorkflowError::DuplicateTask { task_id: task.id });
+            }
+        }
+        for task in tasks.iter() {
+            if let Some(&dependency) = task.dependencies.iter().find(|d| !states.contains_key(d)) {
+                return Err(WorkflowError::UnknownDependency { task_id: task.id, dependency });
+            }
+        }
+
+        // Kahn's algorithm: whatever can't be ordered is part of a cycle
+        let mut done: Vec<u32> = Vec::new();
+        loop {
+            let next: Vec<u32> = tasks
+                .iter()
+                .filter(|t| !done.contains(&t.id) && t.dependencies.iter().all(|d| done.contains(d)))
+                .map(|t| t.id)
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            done.extend(next);
+        }
+        if done.len() < tasks.len() {
+            let mut task_ids: Vec<u32> = tasks.iter().map(|t| t.id).filter(|id| !done.contains(id)).collect();
+            task_ids.sort();
+            return Err(WorkflowError::Cycle { task_ids });
+        }
+
+        Ok(Workflow { id, tasks, states })
+    }
+
+    pub fn status_of(&self, task_id: u32) -> Option<&TaskStatus> {
+        self.states.get(&task_id).map(|s| &s.status)
+    }
+
+    // Pending tasks whose dependencies all succeeded
+    pub fn ready_tasks(&self) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|t| self.states[&t.id].status == TaskStatus::Pending)
+            .filter(|t| t.dependencies.iter().all(|d| self.states[d].status == TaskStatus::Succeeded))
+            .collect()
+    }
+
+    // Task run by a process
+    pub fn task_for_process(&self, process_id: u32) -> Option<u32> {
+        self.states
+            .iter()
+            .find(|(_, s)| s.status == TaskStatus::Running { process_id })
+            .map(|(&id, _)| id)
+    }
+
+    pub fn start(&mut self, task_id: u32, process_id: u32) {
+        if let Some(state) = self.states.get_mut(&task_id) {
+            state.status = TaskStatus::Running { process_id };
+            state.attempts += 1;
+        }
+    }
+
+    pub fn complete(&mut self, task_id: u32) {
+        if let Some(state) = self.states.get_mut(&task_id) {
+            state.status = TaskStatus::Succeeded;
+        }
+    }
+
+    // Record a failed run. Returns true if the task will be retried,
+    // otherwise the task fails and everything depending on it is skipped.
+    pub fn fail(&mut self, task_id: u32, reason: String) -> bool {
+        let max_retries = match self.tasks.iter().find(|t| t.id == task_id) {
+            Some(task) => task.max_retries,
+            None => return false,
+        };
+        let state = self.states.get_mut(&task_id).unwrap();
+        if state.attempts <= max_retries {
+            state.status = TaskStatus::Pending;
+            return true;
+        }
+        state.status = TaskStatus::Failed { reason };
+
+        let mut failed = vec![task_id];
+        while let Some(failed_id) = failed.pop() {
+            for task in self.tasks.iter().filter(|t| t.dependencies.contains(&failed_id)) {
+                let state = self.states.get_mut(&task.id).unwrap();
+                if state.status == TaskStatus::Pending {
+                    state.status = TaskStatus::Skipped { failed_dependency: failed_id };
+                    failed.push(task.id);
+                }
+            }
+        }
+        false
+    }
+
+    pub fn status(&self) -> WorkflowStatus {
+        let states = self.states.values();
+        if states.clone().any(|s| matches!(s.status, TaskStatus::Pending | TaskStatus::Running { .. })) {
+            WorkflowStatus::Running
+        } else if states.clone().all(|s| s.status == TaskStatus::Succeeded) {
+            WorkflowStatus::Succeeded
+        } else {
+            WorkflowStatus::Failed
+        }
+    }
+
+    pub fn report(&self) -> WorkflowReport {
+        let mut report = WorkflowReport {
+            workflow_id: self.id,
+            status: self.status(),
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+            attempts: Vec::new(),
+        };
+        for task in self.tasks.iter() {
+            let state = &self.states[&task.id];
+            match &state.status {
+                TaskStatus::Succeeded => report.succeeded.push(task.id),
+                TaskStatus::Failed { reason } => report.failed.push((task.id, reason.clone())),
+                TaskStatus::Skipped { .. } => report.skipped.push(task.id),
+                TaskStatus::Pending | TaskStatus::Running { .. } => {}
+            }
+            report.attempts.push((task.id, state.attempts));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32, dependencies: Vec<u32>) -> Task {
+        Task::with_dependencies(id, format!("Task {}", id), Resources::new(1_024, 1_000, 1), dependencies)
+    }
+
+    // 1 -> 2 -> 4 and 1 -> 3
+    fn diamond() -> Workflow {
+        Workflow::new(1, vec![task(1, vec![]), task(2, vec![1]), task(3, vec![1]), task(4, vec![2])]).unwrap()
+    }
+
+    fn ready(workflow: &Workflow) -> Vec<u32> {
+        workflow.ready_tasks().iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn test_invalid_workflows() {
+        assert_eq!(
+            Workflow::new(1, vec![task(1, vec![]), task(1, vec![])]),
+            Err(WorkflowError::DuplicateTask { task_id: 1 })
+        );
+        assert_eq!(
+            Workflow::new(1, vec![task(1, vec![7])]),
+            Err(WorkflowError::UnknownDependency { task_id: 1, dependency: 7 })
+        );
+        assert_eq!(
+            Workflow::new(1, vec![task(1, vec![]), task(2, vec![3]), task(3, vec![2])]),
+            Err(WorkflowError::Cycle { task_ids: vec![2, 3] })
+        );
+    }
+
+    #[test]
+    fn test_tasks_start_after_their_dependencies() {
+        let mut workflow = diamond();
+        assert_eq!(ready(&workflow), vec![1]);
+
+        workflow.start(1, 10);
+        assert!(ready(&workflow).is_empty());
+        assert_eq!(workflow.task_for_process(10), Some(1));
+
+        workflow.complete(1);
+        assert_eq!(ready(&workflow), vec![2, 3]);
+        for id in 2..=4 {
+            workflow.start(id, 10 + id);
+            workflow.complete(id);
+        }
+        assert_eq!(workflow.status(), WorkflowStatus::Succeeded);
+    }
+
+    #[test]
+    fn test_failure_skips_dependents() {
+        let mut workflow = diamond();
+        workflow.start(1, 10);
+        workflow.complete(1);
+        workflow.start(2, 11);
+        workflow.start(3, 12);
+
+        assert!(!workflow.fail(2, "Crash".to_string()));
+        assert_eq!(workflow.status_of(4), Some(&TaskStatus::Skipped { failed_dependency: 2 }));
+        // Task 3 doesn't depend on 2 and keeps running
+        assert_eq!(workflow.status(), WorkflowStatus::Running);
+
+        workflow.complete(3);
+        let report = workflow.report();
+        assert_eq!(report.status, WorkflowStatus::Failed);
+        assert_eq!(report.succeeded, vec![1, 3]);
+        assert_eq!(report.failed, vec![(2, "Crash".to_string())]);
+        assert_eq!(report.skipped, vec![4]);
+    }
+
+    #[test]
+    fn test_retries() {
+        let mut flaky = task(1, vec![]);
+        flaky.max_retries = 1;
+        let mut workflow = Workflow::new(1, vec![flaky]).unwrap();
+
+        workflow.start(1, 10);
+        assert!(workflow.fail(1, "Timeout".to_string()));
+        assert_eq!(ready(&workflow), vec![1]);
+
+        workflow.start(1, 11);
+        assert!(!workflow.fail(1, "Timeout".to_string()));
+        assert_eq!(workflow.report().attempts, vec![(1, 2)]);
+        assert_eq!(workflow.status(), WorkflowStatus::Failed);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::process::Process;
+use crate::resource::Resources;
+use std::collections::HashMap;
+
+// Tenant a process is accounted to, processes without owner share an anonymous tenant
+pub fn tenant_of(process: &Process) -> &str {
+    process.tenant.as_deref().unwrap_or("")
+}
+
+// Resources used by every tenant
+pub fn tenant_usage<'a>(processes: impl Iterator<Item = &'a Process>) -> HashMap<String, Resources> {
+    let mut usage: HashMap<String, Resources> = HashMap::new();
+    for process in processes {
+        *usage.entry(tenant_of(process).to_string()).or_insert_with(|| Resources::new(0, 0, 0)) += &process.needed_resources;
+    }
+    usage
+}
+
+// Whether the process fits in what is left of its tenant's quota. Tenants without quota are unlimited.
+pub fn within_quota(quotas: &HashMap<String, Resources>, usage: &HashMap<String, Resources>, process: &Process) -> bool {
+    let tenant = tenant_of(process);
+    let quota = match quotas.get(tenant) {
+        Some(quota) => quota,
+        None => return true,
+    };
+    let requested = usage.get(tenant).map_or(process.needed_resources.clone(), |used| used + &process.needed_resources);
+    quota.can_allocate(&requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(id: u32, tenant: &str, resources: Resources) -> Process {
+        let mut process = Process::new(id, format!("Process {}", id), resources);
+        process.tenant = Some(tenant.to_string());
+        process
+    }
+
+    #[test]
+    fn test_usage_and_quota() {
+        let running = [
+            owned(1, "a", Resources::new(2_048, 1_000, 1)),
+            owned(2, "a", Resources::new(2_048, 1_000, 1)),
+            Process::new(3, "Unowned".to_string(), Resources::new(1_024, 1_000, 1)),
+        ];
+        let usage = tenant_usage(running.iter());
+        assert_eq!(usage["a"], Resources::new(4_096, 2_000, 2));
+        assert_eq!(usage[""], Resources::new(1_024, 1_000, 1));
+
+        let quotas = HashMap::from([("a".to_string(), Resources::new(6_144, 10_000, 4))]);
+        assert!(within_quota(&quotas, &usage, &owned(4, "a", Resources::new(2_048, 1_000, 1))));
+        assert!(!within_quota(&quotas, &usage, &owned(4, "a", Resources::new(4_096, 1_000, 1))));
+        assert!(within_quota(&quotas, &usage, &owned(4, "b", Resources::new(65_536, 1_000, 1))));
+    }
+}
@@ -3,7 +3,10 @@ mod tests {
     use std::sync::Arc;
     use tokio::sync::Mutex;
     use tokio::time::Duration;
-    use sistema_distribuido::{node::Node, session::Session, resource::Resources, process::Process};
+    use sistema_distribuido::{node::Node, session::Session, resource::Resources, process::Process, message::Action};
+    use sistema_distribuido::{clock::Clock, fault::FaultInjector};
+    use sistema_distribuido::rebalance::{RebalanceConfig, Rebalancer};
+    use sistema_distribuido::autoscale::{Autoscaler, AutoscalerConfig, StaticProvider};
 
     #[tokio::test]
     async fn test_balanceo_de_carga() {
@@ -70,7 +73,9 @@ mod tests {
         session.handle_node_failure(1, "Fallo simulado".to_string());
 
         assert!(session.nodes.len() == 2);
-        assert!(session.processes.len() == 1);
+        // El proceso se reasigna de inmediato al nodo superviviente
+        assert!(session.processes.is_empty());
+        assert!(session.nodes.iter().any(|n| n.id == 2 && n.active_processes.len() == 1));
     }
 
     #[test]
@@ -82,9 +87,35 @@ mod tests {
         );
     
         // Agrega un nuevo nodo
-        session.add_node(Node::new(2));
+        session.add_node(Node::with_resources(2, Resources::new(8_192, 500_000, 4)));
     
         assert!(session.total_nodes() == 2);
+        // El nodo nuevo aporta su propia capacidad
+        assert_eq!(session.nodes[1].total_resources, Resources::new(8_192, 500_000, 4));
+        assert_eq!(session.total_resources, Resources::new(24_576, 1_500_000, 12));
+    }
+
+    #[tokio::test]
+    async fn test_autoescalado() {
+        let mut session = Session::new(vec![Node::new(1)], vec![], Resources::new(8_192, 500_000, 4));
+        session.clock = Clock::simulated(0);
+        let proveedor = StaticProvider::new(Resources::new(8_192, 500_000, 4));
+        let config = AutoscalerConfig { max_nodes: 3, scale_up_after: 1_000, cooldown: 1_000, ..AutoscalerConfig::default() };
+        session.autoscaler = Some(Autoscaler::new(config, Box::new(proveedor)));
+
+        // Llega más trabajo del que cabe en un nodo
+        for id in 1..=3 {
+            session.processes.push(Process::new(id, format!("Pesado {}", id), Resources::new(8_192, 100_000, 2)));
+        }
+        for _ in 0..5 {
+            session.assign_processes().await;
+            session.autoscale();
+            session.clock.advance(1_000);
+        }
+        session.assign_processes().await;
+
+        assert_eq!(session.total_nodes(), 3);
+        assert!(session.processes.is_empty());
     }
 
     #[tokio::test]
@@ -108,5 +139,102 @@ mod tests {
         // Verifica redistribución
         assert!(session.nodes[1].active_processes.len() > 0);
     }
+
+    #[tokio::test]
+    async fn test_rebalanceo_continuo() {
+        let mut session = Session::new(
+            vec![Node::new(1), Node::new(2)],
+            vec![],
+            Resources::new(16_384, 1_000_000, 8),
+        );
+        session.clock = Clock::simulated(0);
+        session.rebalancer = Some(Rebalancer::new(RebalanceConfig::default()));
+
+        // Todo queda en el nodo 1
+        for id in 1..=4 {
+            let proceso = Process::new(id, format!("Proceso {}", id), Resources::new(2_048, 100_000, 1));
+            session.allocate_resources(1, &proceso.needed_resources);
+            session.nodes[0].active_processes.push(proceso);
+        }
+
+        // Cada ronda mueve como mucho dos procesos al nodo elegido, hasta equilibrar la carga
+        for _ in 0..3 {
+            session.rebalance();
+            session.clock.advance(10_000);
+        }
+        assert!(session.committed_actions.iter().all(|accion| matches!(
+            accion,
+            Action::RedistributeProcess { target_node_id: Some(2), .. }
+        )));
+        assert_eq!(session.nodes[0].active_processes.len(), 2);
+        assert_eq!(session.nodes[1].active_processes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_particion_de_red() {
+        let nodes = (1..=5).map(Node::new).collect();
+        // Cada nodo recibe 8 GB, 400 GB y 3 hilos
+        let mut session = Session::new(nodes, vec![], Resources::new(40_960, 2_000_000, 16));
+
+        let process = Process::new(1, "Replicado".to_string(), Resources::new(4_096, 200_000, 2));
+        session.allocate_resources(4, &process.needed_resources);
+        session.nodes[3].active_processes.push(process);
+
+        // Particiona la red: el nodo 4 queda en la minoría
+        session.network.partition(vec![vec![1, 2, 3], vec![4, 5]]);
+
+        // La minoría no puede declarar caído a un nodo de la mayoría
+        session.initiate_voting(5, Action::NodeFailure { node_id: 1, reason: "Sin respuesta".to_string() });
+        // La mayoría declara caído al nodo 4 y reasigna su proceso
+        session.initiate_voting(1, Action::NodeFailure { node_id: 4, reason: "Sin respuesta".to_string() });
+        session.assign_processes().await;
+
+        // El proceso se ejecuta una sola vez, en la mayoría
+        let copias: Vec<u32> = session.nodes.iter()
+            .filter(|n| n.active_processes.iter().any(|p| p.id == 1))
+            .map(|n| n.id)
+            .collect();
+        assert_eq!(copias.len(), 1);
+        assert!(copias[0] <= 3);
+    }
+
+    // Ejecuta un experimento de caos con una semilla y devuelve las acciones confirmadas
+    async fn experimento_de_caos(semilla: u64) -> Vec<Action> {
+        let nodes = (1..=5).map(Node::new).collect();
+        let mut session = Session::new(nodes, vec![], Resources::new(16_384, 1_000_000, 8));
+        session.clock = Clock::simulated(0);
+
+        let mut injector = FaultInjector::new(semilla);
+        injector.drop_probability = 0.1;
+        injector.delay_probability = 0.2;
+        injector.duplicate_probability = 0.2;
+        injector.reorder_probability = 0.1;
+        injector.pause_probability = 0.2;
+        session.fault_injector = Some(injector);
+
+        for ronda in 0..20 {
+            session.clock.advance(100);
+            session.inject_faults();
+            session.deliver_messages();
+
+            let proponente = (ronda % 5) + 1;
+            session.request_resource(proponente).await;
+            let action = Action::ProcessFailure { node_id: proponente, reason: format!("Ronda {}", ronda) };
+            session.initiate_voting(proponente, action);
+
+            // Exclusión mutua: nunca dos nodos con acceso al mismo tiempo
+            let con_acceso = (1..=5).filter(|&id| session.can_access_resource(id)).count();
+            assert!(con_acceso <= 1);
+            if session.can_access_resource(proponente) {
+                session.release_resource(proponente);
+            }
+        }
+        session.committed_actions.clone()
+    }
+
+    #[tokio::test]
+    async fn test_inyeccion_de_fallos_reproducible() {
+        assert_eq!(experimento_de_caos(7).await, experimento_de_caos(7).await);
+    }
     
 }